@@ -10,6 +10,36 @@ use std::rc::Rc;
 pub trait LoxCallable: fmt::Display {
     fn call(&self, interpreter: &Interpreter, arguments: Vec<Object>, klass: Option<Rc<Class>>) -> Result<Object, LoxResult>;
     fn arity(&self) -> usize;
+
+    // The fewest arguments this takes, `arity` is the most. Only natives with optional trailing
+    // arguments take fewer than their arity.
+    fn min_arity(&self) -> usize {
+        self.arity()
+    }
+}
+
+// The error message for calling `callee` with `count` arguments, if that's the wrong number.
+pub fn arity_error(callee: &dyn LoxCallable, count: usize) -> Option<String> {
+    let (min, max) = (callee.min_arity(), callee.arity());
+    if (min..=max).contains(&count) {
+        None
+    } else if min == max {
+        Some(format!("Expected {} arguments but got {}.", max, count))
+    } else {
+        Some(format!("Expected {} to {} arguments but got {}.", min, max, count))
+    }
+}
+
+// The function to call and, for classes, the class that gets instantiated.
+pub type CallTarget = (Rc<dyn LoxCallable>, Option<Rc<Class>>);
+
+pub fn as_callable(callee: &Object) -> Option<CallTarget> {
+    match callee {
+        Object::Func(f) => Some((f.clone(), None)),
+        Object::Native(n) => Some((n.func.clone(), None)),
+        Object::Class(c) => Some((c.clone(), Some(Rc::clone(c)))),
+        _ => None,
+    }
 }
 
 #[derive(Clone)]
@@ -25,6 +55,10 @@ impl LoxCallable for Callable {
     fn arity(&self) -> usize {
         self.func.arity()
     }
+
+    fn min_arity(&self) -> usize {
+        self.func.min_arity()
+    }
 }
 
 impl fmt::Display for Callable {
@@ -1,22 +1,54 @@
 use crate::callable::*;
+use crate::environment::*;
 use crate::error::*;
 use crate::instance::Instance;
 use crate::interpreter::*;
 use crate::object::*;
+use crate::stmt::*;
+use std::cell::RefCell;
 use std::collections::HashMap;
 use std::fmt;
 use std::rc::Rc;
 
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Clone)]
 pub struct Class {
     name: String,
     methods: HashMap<String, Object>,
+    // Declared with a leading `class`, called on the class itself and never bound to an instance.
+    static_methods: HashMap<String, Object>,
     superclass: Option<Rc<Class>>,
+    fields: Rc<Vec<Rc<Stmt>>>,
+    closure: Rc<RefCell<Environment>>,
+    // Every method callable on an instance, including inherited ones. Classes can't change after
+    // they're declared, so this is built once and `find_method` doesn't have to walk the
+    // superclass chain on every property access.
+    resolved_methods: HashMap<String, Object>,
 }
 
 impl Class {
-    pub fn new(name: String, superclass: Option<Rc<Class>>, methods:  HashMap<String, Object>) -> Self {
-        Self { name, methods, superclass }
+    pub fn new(
+        name: String,
+        superclass: Option<Rc<Class>>,
+        methods: HashMap<String, Object>,
+        static_methods: HashMap<String, Object>,
+        fields: Rc<Vec<Rc<Stmt>>>,
+        closure: Rc<RefCell<Environment>>,
+    ) -> Self {
+        let mut resolved_methods = match &superclass {
+            Some(superclass) => superclass.resolved_methods.clone(),
+            None => HashMap::new(),
+        };
+        resolved_methods.extend(methods.iter().map(|(k, v)| (k.clone(), v.clone())));
+
+        Self { name, methods, static_methods, superclass, fields, closure, resolved_methods }
+    }
+
+    // Superclass defaults are applied first so a subclass can override them.
+    fn initialize_fields(&self, interpreter: &Interpreter, instance: &Rc<Instance>) -> Result<(), LoxResult> {
+        if let Some(superclass) = &self.superclass {
+            superclass.initialize_fields(interpreter, instance)?;
+        }
+        interpreter.initialize_fields(&self.fields, &self.closure, instance)
     }
 
     pub fn instantiate(
@@ -25,7 +57,10 @@ impl Class {
         arguments: Vec<Object>,
         klass: Rc<Class>,
     ) -> Result<Object, LoxResult> {
-        let instance = Object::Instance(Rc::new(Instance::new(klass)));
+        let instance = Rc::new(Instance::new(klass));
+        self.initialize_fields(interpreter, &instance)?;
+
+        let instance = Object::Instance(instance);
         if let Some(Object::Func(initializer)) = self.find_method("init".to_string()) {
             if let Object::Func(init) = initializer.bind(&instance) {
                 init.call(interpreter, arguments, None)?;
@@ -34,17 +69,44 @@ impl Class {
         Ok(instance)
     }
 
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    // Every method callable on an instance, inherited ones included, sorted by name.
+    pub fn method_names(&self) -> Vec<String> {
+        let mut names = self.resolved_methods.keys().cloned().collect::<Vec<String>>();
+        names.sort();
+        names
+    }
+
     pub fn find_method(&self, name: String) -> Option<Object> {
-        if let Some(method) = self.methods.get(&name) {
-            Some(method.clone())
-        } else if let Some(superclass) = &self.superclass {
-            superclass.find_method(name)
-        } else {
-            None
+        self.resolved_methods.get(&name).cloned()
+    }
+
+    pub fn find_static_method(&self, name: &str) -> Option<Object> {
+        match self.static_methods.get(name) {
+            Some(method) => Some(method.clone()),
+            None => self.superclass.as_ref()?.find_static_method(name),
         }
     }
 }
 
+impl fmt::Debug for Class {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self)
+    }
+}
+
+impl PartialEq for Class {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name
+            && self.methods == other.methods
+            && self.superclass == other.superclass
+            && Rc::ptr_eq(&self.fields, &other.fields)
+    }
+}
+
 impl fmt::Display for Class {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let methods = self
@@ -69,3 +131,44 @@ impl LoxCallable for Class {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::native_functions::*;
+
+    // Natives stand in for methods here, only their identity matters.
+    fn method() -> Object {
+        Object::Native(Rc::new(Native::new("clock", Rc::new(NativeClock))))
+    }
+
+    fn class(name: &str, superclass: Option<Rc<Class>>, methods: &[(&str, &Object)]) -> Rc<Class> {
+        let methods = methods
+            .iter()
+            .map(|(name, method)| (name.to_string(), (*method).clone()))
+            .collect();
+        Rc::new(Class::new(
+            name.to_string(),
+            superclass,
+            methods,
+            HashMap::new(),
+            Rc::new(Vec::new()),
+            Rc::new(RefCell::new(Environment::new())),
+        ))
+    }
+
+    #[test]
+    fn find_method_resolves_inherited_and_overridden_methods() {
+        let (speak, eat, bark) = (method(), method(), method());
+
+        let animal = class("Animal", None, &[("speak", &speak), ("eat", &eat)]);
+        let dog = class("Dog", Some(Rc::clone(&animal)), &[("speak", &bark)]);
+        let puppy = class("Puppy", Some(Rc::clone(&dog)), &[]);
+
+        assert_eq!(animal.find_method("speak".to_string()), Some(speak));
+        assert_eq!(dog.find_method("speak".to_string()), Some(bark.clone()));
+        assert_eq!(puppy.find_method("speak".to_string()), Some(bark));
+        assert_eq!(puppy.find_method("eat".to_string()), Some(eat));
+        assert_eq!(puppy.find_method("fly".to_string()), None);
+    }
+}
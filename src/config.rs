@@ -0,0 +1,16 @@
+// Everything an embedder can tune, in one place. `Config::default()` is how the `rawwr` binary
+// runs.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Config {
+    // Don't flush after every `print`, write the output out when `interpret` returns.
+    pub buffered: bool,
+    // Let the resolver warn about locals read before they are assigned.
+    pub warn_uninitialized: bool,
+    // Reject `instance.init()`, classes have to be called to run their initializer.
+    pub forbid_init_calls: bool,
+    // Let the resolver warn about expression statements like `a == b;` whose value is thrown away
+    // and that can't have side effects.
+    pub warn_unused_results: bool,
+    // Seed for `random()` and `shuffle()`, so a run can be repeated. Taken from the clock if not set.
+    pub seed: Option<u64>,
+}
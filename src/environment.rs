@@ -28,6 +28,10 @@ impl Environment {
         }
     }
 
+    pub fn enclosing(&self) -> Option<Rc<RefCell<Environment>>> {
+        self.enclosing.clone()
+    }
+
     pub fn define(&mut self, name: &str, value: Object) {
         self.values.insert(name.to_string(), value);
     }
@@ -56,8 +60,15 @@ impl Environment {
 
     pub fn assign_at(&mut self, distance: usize, name: &Token, value: &Object) -> Result<(), LoxResult> {
         if distance == 0 {
-            self.values.insert(name.lexeme.clone(), value.clone());
-            Ok(())
+            if let Entry::Occupied(mut object) = self.values.entry(name.lexeme.clone()) {
+                object.insert(value.clone());
+                Ok(())
+            } else {
+                Err(LoxResult::runtime_error(
+                    name,
+                    &format!("Undefined variable '{}'.", &name.lexeme),
+                ))
+            }
         } else {
             self.enclosing.as_ref().unwrap().borrow_mut().assign_at(distance - 1, name, value)
         }
@@ -85,7 +96,7 @@ mod tests {
     #[test]
     fn can_read_from_enclosed_environment() {
         let outter_env = Rc::new(RefCell::new(Environment::new()));
-        let token = Token::new(TokenType::Identifier, "foo".to_string(), None, 0);
+        let token = Token::new(TokenType::Identifier, "foo".to_string(), None, 0, 0);
         outter_env.borrow_mut().define("foo", Object::Num(10.0));
 
         let inner_env = Environment::new_with_enclosing(Rc::clone(&outter_env));
@@ -106,7 +117,7 @@ mod tests {
     #[test]
     fn can_assign_to_variable_in_enclosed_environment() {
         let outter_env = Rc::new(RefCell::new(Environment::new()));
-        let token = Token::new(TokenType::Identifier, "foo".to_string(), None, 0);
+        let token = Token::new(TokenType::Identifier, "foo".to_string(), None, 0, 0);
         outter_env.borrow_mut().define("foo", Object::Num(10.0));
 
         let mut inner_env = Environment::new_with_enclosing(Rc::clone(&outter_env));
@@ -114,4 +125,30 @@ mod tests {
         assert_eq!(inner_env.get(&token).unwrap(), Object::Num(20.0));
         assert_eq!(outter_env.borrow().get(&token).unwrap(), Object::Num(20.0));
     }
+
+    #[test]
+    fn get_at_walks_enclosing_environments() {
+        let outer = Rc::new(RefCell::new(Environment::new()));
+        outer.borrow_mut().define("foo", Object::Num(1.0));
+        let middle = Rc::new(RefCell::new(Environment::new_with_enclosing(Rc::clone(&outer))));
+        middle.borrow_mut().define("foo", Object::Num(2.0));
+        let mut inner = Environment::new_with_enclosing(Rc::clone(&middle));
+        inner.define("foo", Object::Num(3.0));
+
+        assert_eq!(inner.get_at(0, "foo"), Object::Num(3.0));
+        assert_eq!(inner.get_at(1, "foo"), Object::Num(2.0));
+        assert_eq!(inner.get_at(2, "foo"), Object::Num(1.0));
+    }
+
+    #[test]
+    fn assign_at_writes_to_the_enclosing_scope() {
+        let outer = Rc::new(RefCell::new(Environment::new()));
+        outer.borrow_mut().define("foo", Object::Num(1.0));
+        let mut inner = Environment::new_with_enclosing(Rc::clone(&outer));
+        let token = Token::new(TokenType::Identifier, "foo".to_string(), None, 0, 0);
+
+        assert!(inner.assign_at(1, &token, &Object::Num(2.0)).is_ok());
+        assert_eq!(outer.borrow().get_at(0, "foo"), Object::Num(2.0));
+        assert!(inner.assign_at(0, &token, &Object::Num(3.0)).is_err());
+    }
 }
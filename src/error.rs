@@ -5,19 +5,22 @@ use crate::object::*;
 pub enum LoxResult {
     ParseError { token: Token, message: String },
     RuntimeError { token: Token, message: String },
-    Error { line: usize, message: String },
+    Error { line: usize, column: i32, message: String },
     ReturnValue { value: Object },
+    Break,
+    Continue,
     SystemError { message: String },
+    NativeError { message: String },
 }
 
 impl LoxResult {
+    // Not reported here but by whoever handles it, see `Interpreter::interpret`. That way output
+    // printed before the error can be written out first.
     pub fn runtime_error(token: &Token, message: &str) -> LoxResult {
-        let e = LoxResult::RuntimeError {
+        LoxResult::RuntimeError {
             token: token.clone(),
             message: message.to_string(),
-        };
-        e.report("");
-        e
+        }
     }
 
     pub fn parse_error(token: &Token, message: &str) -> LoxResult {
@@ -29,9 +32,10 @@ impl LoxResult {
         e
     }
 
-    pub fn scanner_error(line: usize, message: &str) -> LoxResult {
+    pub fn scanner_error(line: usize, column: i32, message: &str) -> LoxResult {
         let e = LoxResult::Error {
             line,
+            column,
             message: message.to_string(),
         };
         e.report("");
@@ -46,6 +50,14 @@ impl LoxResult {
         e
     }
 
+    // Natives don't know where they were called from, so this is not reported here. The call
+    // site turns it into a runtime error at the closing paren of the call.
+    pub fn native_error(message: &str) -> LoxResult {
+        LoxResult::NativeError {
+            message: message.to_string(),
+        }
+    }
+
     pub fn return_value(value: Object) -> LoxResult {
         LoxResult::ReturnValue { value }
     }
@@ -55,18 +67,24 @@ impl LoxResult {
             LoxResult::ParseError { token, message }
             | LoxResult::RuntimeError { token, message } => {
                 if token.token_type == TokenType::EOF {
-                    eprintln!("[line: {}] at end {}", token.line, message);
+                    eprintln!("[line: {}, col: {}] at end {}", token.line, token.column, message);
                 } else {
-                    eprintln!("[line: {}] at '{}' {}", token.line, token.lexeme, message);
+                    eprintln!(
+                        "[line: {}, col: {}] at '{}' {}",
+                        token.line, token.column, token.lexeme, message
+                    );
                 }
             }
-            LoxResult::Error { line, message } => {
-                eprintln!("[line: {}] Error {}: {}", line, msg, message);
+            LoxResult::Error { line, column, message } => {
+                eprintln!("[line: {}, col: {}] Error {}: {}", line, column, msg, message);
             }
             LoxResult::SystemError { message } => {
                 eprintln!("System error: {}", message);
             }
-            LoxResult::ReturnValue { .. } => ()
+            LoxResult::NativeError { message } => {
+                eprintln!("Error: {}", message);
+            }
+            LoxResult::ReturnValue { .. } | LoxResult::Break | LoxResult::Continue => ()
         }
     }
 }
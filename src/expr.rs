@@ -5,16 +5,22 @@ use crate::error::*;
 use std::rc::Rc;
 use std::hash::{Hash, Hasher};
 
+#[derive(Clone)]
 pub enum Expr {
+    Array(Rc<ArrayExpr>),
     Assign(Rc<AssignExpr>),
     Binary(Rc<BinaryExpr>),
     Call(Rc<CallExpr>),
     Get(Rc<GetExpr>),
     Grouping(Rc<GroupingExpr>),
+    Index(Rc<IndexExpr>),
+    IndexSet(Rc<IndexSetExpr>),
     Literal(Rc<LiteralExpr>),
     Logical(Rc<LogicalExpr>),
+    Map(Rc<MapExpr>),
     Set(Rc<SetExpr>),
     Super(Rc<SuperExpr>),
+    Ternary(Rc<TernaryExpr>),
     This(Rc<ThisExpr>),
     Unary(Rc<UnaryExpr>),
     Variable(Rc<VariableExpr>),
@@ -23,15 +29,20 @@ pub enum Expr {
 impl PartialEq for Expr {
     fn eq(&self, other: &Self) -> bool {
         match (self, other) {
+                  (Expr::Array(a), Expr::Array(b)) => Rc::ptr_eq(a, b),
                   (Expr::Assign(a), Expr::Assign(b)) => Rc::ptr_eq(a, b),
                   (Expr::Binary(a), Expr::Binary(b)) => Rc::ptr_eq(a, b),
                   (Expr::Call(a), Expr::Call(b)) => Rc::ptr_eq(a, b),
                   (Expr::Get(a), Expr::Get(b)) => Rc::ptr_eq(a, b),
                   (Expr::Grouping(a), Expr::Grouping(b)) => Rc::ptr_eq(a, b),
+                  (Expr::Index(a), Expr::Index(b)) => Rc::ptr_eq(a, b),
+                  (Expr::IndexSet(a), Expr::IndexSet(b)) => Rc::ptr_eq(a, b),
                   (Expr::Literal(a), Expr::Literal(b)) => Rc::ptr_eq(a, b),
                   (Expr::Logical(a), Expr::Logical(b)) => Rc::ptr_eq(a, b),
+                  (Expr::Map(a), Expr::Map(b)) => Rc::ptr_eq(a, b),
                   (Expr::Set(a), Expr::Set(b)) => Rc::ptr_eq(a, b),
                   (Expr::Super(a), Expr::Super(b)) => Rc::ptr_eq(a, b),
+                  (Expr::Ternary(a), Expr::Ternary(b)) => Rc::ptr_eq(a, b),
                   (Expr::This(a), Expr::This(b)) => Rc::ptr_eq(a, b),
                   (Expr::Unary(a), Expr::Unary(b)) => Rc::ptr_eq(a, b),
                   (Expr::Variable(a), Expr::Variable(b)) => Rc::ptr_eq(a, b),
@@ -48,6 +59,9 @@ impl Hash for Expr {
         H: Hasher,
     {
         match self {
+        Expr::Array(a) => {
+            hasher.write_usize(Rc::as_ptr(a) as usize);
+        },
         Expr::Assign(a) => {
             hasher.write_usize(Rc::as_ptr(a) as usize);
         },
@@ -63,18 +77,30 @@ impl Hash for Expr {
         Expr::Grouping(a) => {
             hasher.write_usize(Rc::as_ptr(a) as usize);
         },
+        Expr::Index(a) => {
+            hasher.write_usize(Rc::as_ptr(a) as usize);
+        },
+        Expr::IndexSet(a) => {
+            hasher.write_usize(Rc::as_ptr(a) as usize);
+        },
         Expr::Literal(a) => {
             hasher.write_usize(Rc::as_ptr(a) as usize);
         },
         Expr::Logical(a) => {
             hasher.write_usize(Rc::as_ptr(a) as usize);
         },
+        Expr::Map(a) => {
+            hasher.write_usize(Rc::as_ptr(a) as usize);
+        },
         Expr::Set(a) => {
             hasher.write_usize(Rc::as_ptr(a) as usize);
         },
         Expr::Super(a) => {
             hasher.write_usize(Rc::as_ptr(a) as usize);
         },
+        Expr::Ternary(a) => {
+            hasher.write_usize(Rc::as_ptr(a) as usize);
+        },
         Expr::This(a) => {
             hasher.write_usize(Rc::as_ptr(a) as usize);
         },
@@ -90,21 +116,32 @@ impl Hash for Expr {
 impl Expr {
     pub fn accept<T>(&self, wrapper: Rc<Expr>, visitor: &dyn ExprVisitor<T>) -> Result<T, LoxResult> {
         match self {
+            Expr::Array(x) => visitor.visit_array_expr(wrapper, x),
             Expr::Assign(x) => visitor.visit_assign_expr(wrapper, x),
             Expr::Binary(x) => visitor.visit_binary_expr(wrapper, x),
             Expr::Call(x) => visitor.visit_call_expr(wrapper, x),
             Expr::Get(x) => visitor.visit_get_expr(wrapper, x),
             Expr::Grouping(x) => visitor.visit_grouping_expr(wrapper, x),
+            Expr::Index(x) => visitor.visit_index_expr(wrapper, x),
+            Expr::IndexSet(x) => visitor.visit_index_set_expr(wrapper, x),
             Expr::Literal(x) => visitor.visit_literal_expr(wrapper, x),
             Expr::Logical(x) => visitor.visit_logical_expr(wrapper, x),
+            Expr::Map(x) => visitor.visit_map_expr(wrapper, x),
             Expr::Set(x) => visitor.visit_set_expr(wrapper, x),
             Expr::Super(x) => visitor.visit_super_expr(wrapper, x),
+            Expr::Ternary(x) => visitor.visit_ternary_expr(wrapper, x),
             Expr::This(x) => visitor.visit_this_expr(wrapper, x),
             Expr::Unary(x) => visitor.visit_unary_expr(wrapper, x),
             Expr::Variable(x) => visitor.visit_variable_expr(wrapper, x),
         }
     }
 }
+pub struct ArrayExpr {
+    pub open_bracket: Token,
+    pub bracket: Token,
+    pub elements: Vec<Rc<Expr>>,
+}
+
 pub struct AssignExpr {
     pub name: Token,
     pub value: Rc<Expr>,
@@ -131,6 +168,19 @@ pub struct GroupingExpr {
     pub expression: Rc<Expr>,
 }
 
+pub struct IndexExpr {
+    pub object: Rc<Expr>,
+    pub bracket: Token,
+    pub index: Rc<Expr>,
+}
+
+pub struct IndexSetExpr {
+    pub object: Rc<Expr>,
+    pub bracket: Token,
+    pub index: Rc<Expr>,
+    pub value: Rc<Expr>,
+}
+
 pub struct LiteralExpr {
     pub value: Option<Object>,
 }
@@ -141,6 +191,13 @@ pub struct LogicalExpr {
     pub right: Rc<Expr>,
 }
 
+pub struct MapExpr {
+    pub open_brace: Token,
+    pub brace: Token,
+    pub keys: Vec<Rc<Expr>>,
+    pub values: Vec<Rc<Expr>>,
+}
+
 pub struct SetExpr {
     pub object: Rc<Expr>,
     pub name: Token,
@@ -152,6 +209,12 @@ pub struct SuperExpr {
     pub method: Token,
 }
 
+pub struct TernaryExpr {
+    pub condition: Rc<Expr>,
+    pub then_branch: Rc<Expr>,
+    pub else_branch: Rc<Expr>,
+}
+
 pub struct ThisExpr {
     pub keyword: Token,
 }
@@ -166,15 +229,20 @@ pub struct VariableExpr {
 }
 
 pub trait ExprVisitor<T> {
+    fn visit_array_expr(&self, wrapper: Rc<Expr>, expr: &ArrayExpr) -> Result<T, LoxResult>;
     fn visit_assign_expr(&self, wrapper: Rc<Expr>, expr: &AssignExpr) -> Result<T, LoxResult>;
     fn visit_binary_expr(&self, wrapper: Rc<Expr>, expr: &BinaryExpr) -> Result<T, LoxResult>;
     fn visit_call_expr(&self, wrapper: Rc<Expr>, expr: &CallExpr) -> Result<T, LoxResult>;
     fn visit_get_expr(&self, wrapper: Rc<Expr>, expr: &GetExpr) -> Result<T, LoxResult>;
     fn visit_grouping_expr(&self, wrapper: Rc<Expr>, expr: &GroupingExpr) -> Result<T, LoxResult>;
+    fn visit_index_expr(&self, wrapper: Rc<Expr>, expr: &IndexExpr) -> Result<T, LoxResult>;
+    fn visit_index_set_expr(&self, wrapper: Rc<Expr>, expr: &IndexSetExpr) -> Result<T, LoxResult>;
     fn visit_literal_expr(&self, wrapper: Rc<Expr>, expr: &LiteralExpr) -> Result<T, LoxResult>;
     fn visit_logical_expr(&self, wrapper: Rc<Expr>, expr: &LogicalExpr) -> Result<T, LoxResult>;
+    fn visit_map_expr(&self, wrapper: Rc<Expr>, expr: &MapExpr) -> Result<T, LoxResult>;
     fn visit_set_expr(&self, wrapper: Rc<Expr>, expr: &SetExpr) -> Result<T, LoxResult>;
     fn visit_super_expr(&self, wrapper: Rc<Expr>, expr: &SuperExpr) -> Result<T, LoxResult>;
+    fn visit_ternary_expr(&self, wrapper: Rc<Expr>, expr: &TernaryExpr) -> Result<T, LoxResult>;
     fn visit_this_expr(&self, wrapper: Rc<Expr>, expr: &ThisExpr) -> Result<T, LoxResult>;
     fn visit_unary_expr(&self, wrapper: Rc<Expr>, expr: &UnaryExpr) -> Result<T, LoxResult>;
     fn visit_variable_expr(&self, wrapper: Rc<Expr>, expr: &VariableExpr) -> Result<T, LoxResult>;
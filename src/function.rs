@@ -17,6 +17,9 @@ pub struct Function {
     body: Rc<Vec<Rc<Stmt>>>,
     closure: Rc<RefCell<Environment>>,
     is_initializer: bool,
+    is_getter: bool,
+    // Bound to an instance, `closure` is then the environment holding `this`.
+    is_bound: bool,
 }
 
 impl Function {
@@ -31,11 +34,41 @@ impl Function {
             body: Rc::clone(&declaration.body),
             closure: Rc::clone(closure),
             is_initializer,
+            is_getter: declaration.is_getter,
+            is_bound: false,
         }
     }
 
+    pub fn name(&self) -> &str {
+        &self.name.lexeme
+    }
+
+    pub fn is_getter(&self) -> bool {
+        self.is_getter
+    }
+
+    // Binds the method to `instance` and, for a getter, runs it right away to get the property.
+    pub fn get_bound(&self, interpreter: &Interpreter, instance: &Object, property: &Token) -> Result<Object, LoxResult> {
+        let method = self.bind(instance);
+        if self.is_getter {
+            interpreter.call_getter(&method, property)
+        } else {
+            Ok(method)
+        }
+    }
+
+    pub fn is_bound(&self) -> bool {
+        self.is_bound
+    }
+
+    // Binding an already bound method replaces its `this`, it doesn't add another one on top.
     pub fn bind(&self, instance: &Object) -> Object {
-        let mut env = Environment::new_with_enclosing(Rc::clone(&self.closure));
+        let closure = if self.is_bound {
+            self.closure.borrow().enclosing().expect("a bound method has an enclosing environment")
+        } else {
+            Rc::clone(&self.closure)
+        };
+        let mut env = Environment::new_with_enclosing(closure);
         env.define("this", instance.clone());
 
         Object::Func(Rc::new(Self {
@@ -44,6 +77,8 @@ impl Function {
             body: Rc::clone(&self.body),
             closure: Rc::new(RefCell::new(env)),
             is_initializer: self.is_initializer,
+            is_getter: self.is_getter,
+            is_bound: true,
         }))
     }
 }
@@ -62,6 +97,8 @@ impl Clone for Function {
             body: Rc::clone(&self.body),
             closure: Rc::clone(&self.closure),
             is_initializer: self.is_initializer,
+            is_getter: self.is_getter,
+            is_bound: self.is_bound,
         }
     }
 }
@@ -1,6 +1,18 @@
 use std::fs::File;
 use std::io::Write;
 
+// `IndexSet` -> `index_set`, for the visitor method names.
+fn snake_case(name: &str) -> String {
+    let mut snake = String::new();
+    for (i, c) in name.chars().enumerate() {
+        if c.is_uppercase() && i > 0 {
+            snake.push('_');
+        }
+        snake.push(c.to_ascii_lowercase());
+    }
+    snake
+}
+
 struct Type {
     base_name: String,
     fields: String,
@@ -36,7 +48,10 @@ pub fn generate_ast(
         });
     }
 
-    writeln!(file, "\npub enum {} {{", base_name)?;
+    // Every variant is an `Rc`, so cloning only bumps a reference count and the clone is still
+    // equal to (and hashes like) the original.
+    writeln!(file, "\n#[derive(Clone)]")?;
+    writeln!(file, "pub enum {} {{", base_name)?;
     for t in &ttypes {
         //if base_name == "Expr" {
             writeln!(file, "    {}(Rc<{}{}>),", t.base_name, t.base_name, base_name)?;
@@ -89,7 +104,7 @@ pub fn generate_ast(
         writeln!(
             file,
             "            {}::{}(x) => visitor.visit_{}_{}(wrapper, x),",
-            base_name, t.base_name, t.base_name.to_lowercase(), base_name.to_lowercase()
+            base_name, t.base_name, snake_case(&t.base_name), base_name.to_lowercase()
         )?;
     }
     writeln!(file, "        }}")?;
@@ -111,7 +126,7 @@ pub fn generate_ast(
         writeln!(
             file,
             "    fn visit_{0}_{1}(&self, wrapper: Rc<{3}>, {1}: &{2}{3}) -> Result<T, LoxResult>;",
-            t.base_name.to_lowercase(),
+            snake_case(&t.base_name),
             base_name.to_lowercase(),
             t.base_name,
             base_name,
@@ -142,30 +157,39 @@ pub fn generate_ast(
 
 fn main() -> Result<(), std::io::Error> {
     generate_ast("./src".to_string(), "Expr".to_string(), &vec![
+        "Array    : Token open_bracket, Token bracket, Vec<Rc<Expr>> elements".to_string(),
         "Assign   : Token name, Rc<Expr> value".to_string(),
         "Binary   : Rc<Expr> left, Token operator, Rc<Expr> right".to_string(),
         "Call     : Rc<Expr> callee, Token paren, Vec<Rc<Expr>> arguments".to_string(),
         "Get      : Rc<Expr> object, Token name".to_string(),
         "Grouping : Rc<Expr> expression".to_string(),
+        "Index    : Rc<Expr> object, Token bracket, Rc<Expr> index".to_string(),
+        "IndexSet : Rc<Expr> object, Token bracket, Rc<Expr> index, Rc<Expr> value".to_string(),
         "Literal  : Option<Object> value".to_string(),
         "Logical  : Rc<Expr> left, Token operator, Rc<Expr> right".to_string(),
+        "Map      : Token open_brace, Token brace, Vec<Rc<Expr>> keys, Vec<Rc<Expr>> values".to_string(),
         "Set      : Rc<Expr> object, Token name, Rc<Expr> value".to_string(),
         "Super    : Token keyword, Token method".to_string(),
+        "Ternary  : Rc<Expr> condition, Rc<Expr> then_branch, Rc<Expr> else_branch".to_string(),
         "This     : Token keyword".to_string(),
         "Unary    : Token operator, Rc<Expr> right".to_string(),
         "Variable : Token name".to_string(),
     ])?;
 
+    // Statements that don't hold on to their first token keep its line instead, so every statement
+    // knows where it starts.
     generate_ast("./src".to_string(), "Stmt".to_string(), &vec![
-        "Block          : Rc<Vec<Rc<Stmt>>> statements".to_string(),
-        "Class          : Token name, Option<Rc<Expr>> superclass, Rc<Vec<Rc<Stmt>>> methods".to_string(),
-        "Expression     : Rc<Expr> expression".to_string(),
-        "Function       : Token name, Rc<Vec<Token>> params, Rc<Vec<Rc<Stmt>>> body".to_string(),
-        "If             : Rc<Expr> condition, Rc<Stmt> then_branch, Option<Rc<Stmt>> else_branch".to_string(),
-        "Print          : Rc<Expr> expression".to_string(),
+        "Block          : Rc<Vec<Rc<Stmt>>> statements, i32 line".to_string(),
+        "Break          : Token keyword".to_string(),
+        "Class          : Token name, Option<Rc<Expr>> superclass, Rc<Vec<Rc<Stmt>>> fields, Rc<Vec<Rc<Stmt>>> methods, Rc<Vec<Rc<Stmt>>> static_methods".to_string(),
+        "Continue       : Token keyword".to_string(),
+        "Expression     : Rc<Expr> expression, i32 line".to_string(),
+        "Function       : Token name, Rc<Vec<Token>> params, Rc<Vec<Rc<Stmt>>> body, bool is_getter".to_string(),
+        "If             : Rc<Expr> condition, Rc<Stmt> then_branch, Option<Rc<Stmt>> else_branch, i32 line".to_string(),
+        "Print          : Rc<Expr> expression, i32 line".to_string(),
         "Return         : Token keyword, Option<Rc<Expr>> value".to_string(),
         "Var            : Token name, Option<Rc<Expr>> initializer".to_string(),
-        "While          : Rc<Expr> condition, Rc<Stmt> body".to_string(),
+        "While          : Rc<Expr> condition, Rc<Stmt> body, Option<Rc<Expr>> increment, Vec<Token> loop_variables, i32 line".to_string(),
     ])?;
 
     Ok(())
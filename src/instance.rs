@@ -1,33 +1,52 @@
 use crate::class::*;
 use crate::error::*;
+use crate::interpreter::Interpreter;
 use crate::object::*;
 use crate::token::*;
 use std::cell::RefCell;
-use std::collections::hash_map::Entry;
-use std::collections::HashMap;
 use std::fmt;
 use std::rc::Rc;
 
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, Clone)]
 pub struct Instance {
     klass: Rc<Class>,
-    fields: RefCell<HashMap<String, Object>>,
+    // Kept in the order the fields were first set, so listing them is deterministic.
+    fields: RefCell<Vec<(String, Object)>>,
+}
+
+// Two instances are only equal if they are the same instance, whatever their fields hold.
+impl PartialEq for Instance {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
 }
 
 impl Instance {
     pub fn new(klass: Rc<Class>) -> Self {
         Self {
             klass: Rc::clone(&klass),
-            fields: RefCell::new(HashMap::new()),
+            fields: RefCell::new(Vec::new()),
         }
     }
 
-    pub fn get(&self, name: &Token, this: &Rc<Instance>) -> Result<Object, LoxResult> {
-        if let Entry::Occupied(o) = self.fields.borrow_mut().entry(name.lexeme.clone()) {
-            Ok(o.get().clone())
+    pub fn class(&self) -> &Rc<Class> {
+        &self.klass
+    }
+
+    // Methods are bound to the instance they're looked up on, which is why this takes the `Rc`.
+    pub fn get(self: &Rc<Instance>, interpreter: &Interpreter, name: &Token) -> Result<Object, LoxResult> {
+        // looked up first so `fields` isn't borrowed anymore when a getter runs
+        let field = self
+            .fields
+            .borrow()
+            .iter()
+            .find(|(field, _)| *field == name.lexeme)
+            .map(|(_, value)| value.clone());
+        if let Some(value) = field {
+            Ok(value)
         } else if let Some(method) = self.klass.find_method(name.lexeme.clone()) {
             if let Object::Func(func) = method {
-                Ok(func.bind(&Object::Instance(Rc::clone(this))))
+                func.get_bound(interpreter, &Object::Instance(Rc::clone(self)), name)
             } else {
                 Err(LoxResult::runtime_error(name, "'this' is defined on a non-function"))
             }
@@ -40,21 +59,35 @@ impl Instance {
     }
 
     pub fn set(&self, name: &Token, value: &Object) -> Result<(), LoxResult> {
-        self.fields.borrow_mut().insert(name.lexeme.clone(), value.clone());
+        self.set_field(&name.lexeme, value.clone());
         Ok(())
     }
+
+    // Overwriting a field keeps its original position.
+    fn set_field(&self, name: &str, value: Object) {
+        let mut fields = self.fields.borrow_mut();
+        match fields.iter_mut().find(|(field, _)| field == name) {
+            Some((_, old)) => *old = value,
+            None => fields.push((name.to_string(), value)),
+        }
+    }
+
+    // The fields in the order they were first set.
+    pub fn fields(&self) -> Vec<(String, Object)> {
+        self.fields.borrow().clone()
+    }
+
+    // Copies every field of `source` onto this instance, overwriting fields that exist on both.
+    pub fn copy_fields_from(&self, source: &Instance) {
+        // cloned first because `source` might be this very instance
+        for (name, value) in source.fields() {
+            self.set_field(&name, value);
+        }
+    }
 }
 
 impl fmt::Display for Instance {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let mut fields = Vec::new();
-        for (k, v) in self.fields.borrow().iter() {
-            if let Object::Str(value) = v {
-                fields.push(format!("{k} = \"{v}\""));
-            } else {
-                fields.push(format!("{k} = {v}"));
-            }
-        }
-        write!(f, "{} with {{ {} }}", self.klass, fields.join(", "))
+        write!(f, "<{} instance>", self.klass.name())
     }
 }
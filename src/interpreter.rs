@@ -1,23 +1,45 @@
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell, RefMut};
 use std::collections::HashMap;
+use std::io::{self, BufWriter, Write};
 use std::ops::Deref;
 use std::rc::Rc;
+use std::time::{Duration, Instant};
 
 use crate::callable::*;
 use crate::class::*;
+use crate::config::Config;
 use crate::environment::*;
 use crate::error::*;
 use crate::expr::*;
 use crate::function::*;
+use crate::instance::Instance;
 use crate::native_functions::*;
 use crate::object::Object;
+use crate::random::Rng;
 use crate::stmt::*;
 use crate::token::*;
 
+// A call that hasn't returned yet: who was called and the line of the call. Calls an embedder
+// makes through `call_value` aren't on any line, they get 0.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Frame {
+    pub name: String,
+    pub line: i32,
+}
+
+// Called with the line and kind of every statement right before it runs.
+pub type StepHook = Box<dyn FnMut(u32, &str)>;
+
 pub struct Interpreter {
     environment: RefCell<Rc<RefCell<Environment>>>,
     pub globals: Rc<RefCell<Environment>>,
     locals: RefCell<HashMap<Rc<Expr>, usize>>,
+    output: RefCell<BufWriter<Box<dyn Write>>>,
+    config: Config,
+    deadline: Cell<Option<Instant>>,
+    step_hook: RefCell<Option<StepHook>>,
+    call_stack: RefCell<Vec<Frame>>,
+    rng: RefCell<Rng>,
 }
 
 impl StmtVisitor<()> for Interpreter {
@@ -66,10 +88,21 @@ impl StmtVisitor<()> for Interpreter {
                 panic!("");
             };
         }
+
+        let mut static_methods = HashMap::new();
+        for method in stmt.static_methods.deref() {
+            if let Stmt::Function(method) = method.deref() {
+                let function = Function::new(method, &self.environment.borrow(), false);
+                static_methods.insert(method.name.lexeme.clone(), Object::Func(Rc::new(function)));
+            }
+        }
         let klass = Object::Class(Rc::new(Class::new(
             stmt.name.lexeme.clone(),
             superclass,
             methods,
+            static_methods,
+            Rc::clone(&stmt.fields),
+            Rc::clone(&self.environment.borrow()),
         )));
 
         if let Some(prev) = enclosing {
@@ -90,8 +123,7 @@ impl StmtVisitor<()> for Interpreter {
 
     fn visit_print_stmt(&self, _: Rc<Stmt>, stmt: &PrintStmt) -> Result<(), LoxResult> {
         let value = self.evaluate(stmt.expression.clone())?;
-        println!("{}", value);
-        Ok(())
+        self.print(&value)
     }
 
     fn visit_var_stmt(&self, _: Rc<Stmt>, stmt: &VarStmt) -> Result<(), LoxResult> {
@@ -125,13 +157,34 @@ impl StmtVisitor<()> for Interpreter {
 
     fn visit_while_stmt(&self, _: Rc<Stmt>, stmt: &WhileStmt) -> Result<(), LoxResult> {
         while self.is_truthy(&self.evaluate(stmt.condition.clone())?) {
-            self.execute(stmt.body.clone())?;
+            let result = if stmt.loop_variables.is_empty() {
+                self.execute(stmt.body.clone())
+            } else {
+                self.execute_iteration(&stmt.loop_variables, stmt.body.clone())
+            };
+            match result {
+                Ok(()) | Err(LoxResult::Continue) => {}
+                Err(LoxResult::Break) => break,
+                Err(e) => return Err(e),
+            }
+            if let Some(increment) = &stmt.increment {
+                self.evaluate(increment.clone())?;
+            }
+            self.check_deadline()?;
         }
         Ok(())
     }
 
+    fn visit_break_stmt(&self, _: Rc<Stmt>, _: &BreakStmt) -> Result<(), LoxResult> {
+        Err(LoxResult::Break)
+    }
+
+    fn visit_continue_stmt(&self, _: Rc<Stmt>, _: &ContinueStmt) -> Result<(), LoxResult> {
+        Err(LoxResult::Continue)
+    }
+
     fn visit_function_stmt(&self, _: Rc<Stmt>, stmt: &FunctionStmt) -> Result<(), LoxResult> {
-        let function = Function::new(stmt, &*self.environment.borrow(), false);
+        let function = Function::new(stmt, &self.environment.borrow(), false);
         self.environment
             .borrow()
             .borrow_mut()
@@ -163,7 +216,7 @@ impl ExprVisitor<Object> for Interpreter {
             let method = sc.find_method(expr.method.lexeme.clone());
             if let Some(method) = method {
                 if let Object::Func(func) = method {
-                    Ok(func.bind(&object))
+                    func.get_bound(self, &object, &expr.method)
                 } else {
                     panic!("method was not found");
                 }
@@ -193,10 +246,74 @@ impl ExprVisitor<Object> for Interpreter {
         }
     }
 
+    fn visit_array_expr(&self, _: Rc<Expr>, expr: &ArrayExpr) -> Result<Object, LoxResult> {
+        let elements = expr
+            .elements
+            .iter()
+            .map(|element| self.evaluate(element.clone()))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Object::array(elements))
+    }
+
+    fn visit_map_expr(&self, _: Rc<Expr>, expr: &MapExpr) -> Result<Object, LoxResult> {
+        let mut map = HashMap::new();
+        for (key, value) in expr.keys.iter().zip(&expr.values) {
+            let key = map_key(&expr.brace, &self.evaluate(key.clone())?)?;
+            map.insert(key, self.evaluate(value.clone())?);
+        }
+        Ok(Object::map(map))
+    }
+
+    // A key missing from a map reads as nil.
+    fn visit_index_expr(&self, _: Rc<Expr>, expr: &IndexExpr) -> Result<Object, LoxResult> {
+        let object = self.evaluate(expr.object.clone())?;
+        let index = self.evaluate(expr.index.clone())?;
+
+        match object {
+            Object::Array(array) => {
+                let array = array.borrow();
+                let index = array_index(&expr.bracket, &index, array.len())?;
+                Ok(array[index].clone())
+            }
+            Object::Map(map) => {
+                let key = map_key(&expr.bracket, &index)?;
+                Ok(map.borrow().get(&key).cloned().unwrap_or(Object::Nil))
+            }
+            _ => Err(LoxResult::runtime_error(&expr.bracket, "Only arrays and maps can be indexed.")),
+        }
+    }
+
+    fn visit_index_set_expr(&self, _: Rc<Expr>, expr: &IndexSetExpr) -> Result<Object, LoxResult> {
+        let object = self.evaluate(expr.object.clone())?;
+        let index = self.evaluate(expr.index.clone())?;
+        let value = self.evaluate(expr.value.clone())?;
+
+        match object {
+            Object::Array(array) => {
+                let mut array = array.borrow_mut();
+                let index = array_index(&expr.bracket, &index, array.len())?;
+                array[index] = value.clone();
+            }
+            Object::Map(map) => {
+                let key = map_key(&expr.bracket, &index)?;
+                map.borrow_mut().insert(key, value.clone());
+            }
+            _ => return Err(LoxResult::runtime_error(&expr.bracket, "Only arrays and maps can be indexed.")),
+        }
+        Ok(value)
+    }
+
     fn visit_get_expr(&self, _: Rc<Expr>, expr: &GetExpr) -> Result<Object, LoxResult> {
         let object = self.evaluate(expr.object.clone())?;
         if let Object::Instance(inst) = object {
-            inst.get(&expr.name, &inst)
+            inst.get(self, &expr.name)
+        } else if let Object::Class(klass) = object {
+            klass.find_static_method(&expr.name.lexeme).ok_or_else(|| {
+                LoxResult::runtime_error(
+                    &expr.name,
+                    &format!("Undefined static method '{}'.", expr.name.lexeme),
+                )
+            })
         } else {
             Err(LoxResult::runtime_error(
                 &expr.name,
@@ -217,9 +334,27 @@ impl ExprVisitor<Object> for Interpreter {
         let left = self.evaluate(expr.left.clone())?;
         let right = self.evaluate(expr.right.clone())?;
 
+        // usually a variable that was never assigned, so it gets its own message
+        let arithmetic = matches!(
+            expr.operator.token_type,
+            TokenType::Star | TokenType::Slash | TokenType::Percent | TokenType::Minus | TokenType::Plus
+        );
+        if arithmetic && (left == Object::Nil || right == Object::Nil) {
+            return Err(LoxResult::runtime_error(&expr.operator, "Operand is nil."));
+        }
+
+        let comparison = matches!(
+            expr.operator.token_type,
+            TokenType::Greater | TokenType::GreaterEqual | TokenType::Less | TokenType::LessEqual
+        );
+        if comparison && !matches!((&left, &right), (Object::Num(_), Object::Num(_))) {
+            return Err(LoxResult::runtime_error(&expr.operator, "Operands must be numbers."));
+        }
+
         let result = match expr.operator.token_type {
             TokenType::Star => left * right,
             TokenType::Slash => left / right,
+            TokenType::Percent => left % right,
             TokenType::Minus => left - right,
             TokenType::Plus => left + right,
             TokenType::Greater => Object::Bool(left > right),
@@ -245,8 +380,8 @@ impl ExprVisitor<Object> for Interpreter {
 
         match expr.operator.token_type {
             TokenType::Minus => match right {
-                Object::Num(n) => Ok(Object::Num(n * (-1_f64))),
-                _ => Ok(Object::Nil),
+                Object::Num(n) => Ok(Object::Num(-n)),
+                _ => Err(LoxResult::runtime_error(&expr.operator, "Operand must be a number.")),
             },
             TokenType::Bang => Ok(Object::Bool(!self.is_truthy(&right))),
             _ => Err(LoxResult::runtime_error(&expr.operator, "Unreachable")),
@@ -279,6 +414,14 @@ impl ExprVisitor<Object> for Interpreter {
         Ok(value)
     }
 
+    fn visit_ternary_expr(&self, _: Rc<Expr>, expr: &TernaryExpr) -> Result<Object, LoxResult> {
+        if self.is_truthy(&self.evaluate(expr.condition.clone())?) {
+            self.evaluate(expr.then_branch.clone())
+        } else {
+            self.evaluate(expr.else_branch.clone())
+        }
+    }
+
     fn visit_logical_expr(&self, _: Rc<Expr>, expr: &LogicalExpr) -> Result<Object, LoxResult> {
         let left = self.evaluate(expr.left.clone())?;
 
@@ -300,28 +443,18 @@ impl ExprVisitor<Object> for Interpreter {
             arguments.push(self.evaluate(argument.clone())?);
         }
 
-        let (callfunc, klass): (Option<Rc<dyn LoxCallable>>, Option<Rc<Class>>) = match callee {
-            Object::Func(f) => (Some(f), None),
-            Object::Native(n) => (Some(n.func.clone()), None),
-            Object::Class(c) => {
-                let klass = Rc::clone(&c);
-                (Some(c), Some(klass))
-            }
-            _ => (None, None),
-        };
+        self.check_deadline()?;
 
-        if let Some(callfunc) = callfunc {
-            if arguments.len() != callfunc.arity() {
-                return Err(LoxResult::runtime_error(
-                    &expr.paren,
-                    &format!(
-                        "Expected {} arguments but got {}.",
-                        callfunc.arity(),
-                        arguments.len()
-                    ),
-                ));
+        if let Some((callfunc, klass)) = as_callable(&callee) {
+            if let Some(message) = arity_error(callfunc.as_ref(), arguments.len()) {
+                return Err(LoxResult::runtime_error(&expr.paren, &message));
+            }
+            match self.call_in_frame(&callee, expr.paren.line, callfunc, arguments, klass) {
+                Err(LoxResult::NativeError { message }) => {
+                    Err(LoxResult::runtime_error(&expr.paren, &message))
+                }
+                result => result,
             }
-            callfunc.call(self, arguments, klass)
         } else {
             Err(LoxResult::runtime_error(
                 &expr.paren,
@@ -334,19 +467,93 @@ impl ExprVisitor<Object> for Interpreter {
 impl Interpreter {
     pub fn new() -> Self {
         let globals = Rc::new(RefCell::new(Environment::new()));
-        globals.borrow_mut().define(
-            "clock",
-            Object::Native(Rc::new(Native {
-                func: Rc::new(NativeClock),
-            })),
-        );
+        let define_native = |name: &str, func: Rc<dyn LoxCallable>| {
+            globals
+                .borrow_mut()
+                .define(name, Object::Native(Rc::new(Native::new(name, func))));
+        };
 
-        globals.borrow_mut().define(
-            "num_to_str",
-            Object::Native(Rc::new(Native {
-                func: Rc::new(NativeNumToString),
-            })),
-        );
+        define_native("clock", Rc::new(NativeClock));
+        define_native("num_to_str", Rc::new(NativeNumToString));
+        define_native("str_to_num", Rc::new(NativeStrToNum));
+        define_native("div_round", Rc::new(NativeDivRound));
+        define_native("hash", Rc::new(NativeHash));
+        define_native("is_nan", Rc::new(NativeIsNan));
+        define_native("is_inf", Rc::new(NativeIsInf));
+        define_native("is_finite", Rc::new(NativeIsFinite));
+        define_native("enumerate", Rc::new(NativeEnumerate));
+        define_native("partial", Rc::new(NativePartial));
+        define_native("sort_total", Rc::new(NativeSortTotal));
+        define_native("object_id", Rc::new(NativeObjectId));
+        define_native("with_timeout", Rc::new(NativeWithTimeout));
+        define_native("assign_fields", Rc::new(NativeAssignFields));
+        define_native("group_by", Rc::new(NativeGroupBy));
+        define_native("trim", Rc::new(NativeTrim));
+        define_native("trim_start", Rc::new(NativeTrimStart));
+        define_native("trim_end", Rc::new(NativeTrimEnd));
+        define_native("replace", Rc::new(NativeReplace));
+        define_native("starts_with", Rc::new(NativeStartsWith));
+        define_native("ends_with", Rc::new(NativeEndsWith));
+        define_native("floor_mod", Rc::new(NativeFloorMod));
+        define_native("reverse", Rc::new(NativeReverse));
+        define_native("map_from_pairs", Rc::new(NativeMapFromPairs));
+        define_native("entries", Rc::new(NativeEntries));
+        define_native("unique", Rc::new(NativeUnique));
+        define_native("compose", Rc::new(NativeCompose));
+        define_native("panic", Rc::new(NativePanic));
+        define_native("eq_ignore_case", Rc::new(NativeEqualsIgnoreCase));
+        define_native("flatten", Rc::new(NativeFlatten));
+        define_native("repeat_string", Rc::new(NativeRepeatString));
+        define_native("count", Rc::new(NativeCount));
+        define_native("date_parts", Rc::new(NativeDateParts));
+        define_native("to_fixed", Rc::new(NativeToFixed));
+        define_native("swap", Rc::new(NativeSwap));
+        define_native("map_get", Rc::new(NativeMapGet));
+        define_native("convert", Rc::new(NativeConvert));
+        define_native("is_number", Rc::new(NativeTypePredicate::new(|o| matches!(o, Object::Num(_)))));
+        define_native("is_string", Rc::new(NativeTypePredicate::new(|o| matches!(o, Object::Str(_)))));
+        define_native("is_bool", Rc::new(NativeTypePredicate::new(|o| matches!(o, Object::Bool(_)))));
+        define_native("is_nil", Rc::new(NativeTypePredicate::new(|o| matches!(o, Object::Nil))));
+        define_native("is_function", Rc::new(NativeTypePredicate::new(|o| matches!(o, Object::Func(_) | Object::Native(_)))));
+        define_native("is_class", Rc::new(NativeTypePredicate::new(|o| matches!(o, Object::Class(_)))));
+        define_native("is_instance", Rc::new(NativeTypePredicate::new(|o| matches!(o, Object::Instance(_)))));
+        define_native("is_array", Rc::new(NativeTypePredicate::new(|o| matches!(o, Object::Array(_)))));
+        define_native("is_map", Rc::new(NativeTypePredicate::new(|o| matches!(o, Object::Map(_)))));
+        define_native("clamp_index", Rc::new(NativeClampIndex));
+        define_native("pad_start", Rc::new(NativePadStart));
+        define_native("pad_end", Rc::new(NativePadEnd));
+        define_native("max_by", Rc::new(NativeMaxBy));
+        define_native("min_by", Rc::new(NativeMinBy));
+        define_native("len", Rc::new(NativeLen));
+        define_native("push", Rc::new(NativePush));
+        define_native("pop", Rc::new(NativePop));
+        define_native("keys", Rc::new(NativeKeys));
+        define_native("has", Rc::new(NativeHas));
+        define_native("random", Rc::new(NativeRandom));
+        define_native("seed", Rc::new(NativeSeed));
+        define_native("shuffle", Rc::new(NativeShuffle));
+        define_native("sample", Rc::new(NativeSample));
+        define_native("bind", Rc::new(NativeBindThis));
+        define_native("expect", Rc::new(NativeExpect));
+        define_native("fields", Rc::new(NativeFields));
+        define_native("class_name", Rc::new(NativeClassName));
+        define_native("method_names", Rc::new(NativeMethodNames));
+        define_native("sqrt", Rc::new(NativeSqrt));
+        define_native("pow", Rc::new(NativePow));
+        define_native("floor", Rc::new(NativeFloor));
+        define_native("ceil", Rc::new(NativeCeil));
+        define_native("abs", Rc::new(NativeAbs));
+        define_native("map_merge", Rc::new(NativeMapMerge));
+        define_native("substring", Rc::new(NativeSubstring));
+        define_native("upper", Rc::new(NativeUpper));
+        define_native("lower", Rc::new(NativeLower));
+        define_native("type", Rc::new(NativeType));
+        define_native("zip_with", Rc::new(NativeZipWith));
+        define_native("string_builder", Rc::new(NativeStringBuilder));
+        define_native("sb_append", Rc::new(NativeStringBuilderAppend));
+        define_native("sb_build", Rc::new(NativeStringBuilderBuild));
+        define_native("char_at", Rc::new(NativeCharAt));
+        define_native("assert", Rc::new(NativeAssert));
 
         // println!("{:?}", globals);
 
@@ -354,16 +561,102 @@ impl Interpreter {
             globals: Rc::clone(&globals),
             environment: RefCell::new(Rc::clone(&globals)),
             locals: RefCell::new(HashMap::new()),
+            output: RefCell::new(BufWriter::new(Box::new(io::stdout()))),
+            config: Config::default(),
+            deadline: Cell::new(None),
+            step_hook: RefCell::new(None),
+            call_stack: RefCell::new(Vec::new()),
+            rng: RefCell::new(Rng::from_clock()),
+        }
+    }
+
+    // Sends the output of `print` somewhere other than stdout.
+    pub fn with_output(mut self, output: Box<dyn Write>) -> Self {
+        self.output = RefCell::new(BufWriter::new(output));
+        self
+    }
+
+    // In buffered mode `print` doesn't flush after every line, the output is written out when
+    // `interpret` returns instead, before the error that stopped the program is reported.
+    pub fn buffered(mut self, buffered: bool) -> Self {
+        self.config.buffered = buffered;
+        self
+    }
+
+    pub fn with_config(config: Config) -> Self {
+        let rng = config.seed.map_or_else(Rng::from_clock, Rng::new);
+        Interpreter {
+            config,
+            rng: RefCell::new(rng),
+            ..Interpreter::new()
         }
     }
 
+    // Shared by every native that needs randomness, so one seed makes a whole run reproducible.
+    pub fn rng(&self) -> RefMut<'_, Rng> {
+        self.rng.borrow_mut()
+    }
+
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    pub fn flush(&self) -> io::Result<()> {
+        self.output.borrow_mut().flush()
+    }
+
+    // Lets an embedder follow execution one statement at a time, e.g. to implement breakpoints.
+    // `None` removes the hook again.
+    pub fn set_step_hook(&self, hook: Option<StepHook>) {
+        self.step_hook.replace(hook);
+    }
+
     pub fn interpret(&self, statements: Rc<Vec<Rc<Stmt>>>) -> bool {
-        for statement in statements.deref() {
-            if let Err(e) = self.execute(statement.clone()) {
-                return false;
-            }
+        let result = statements
+            .iter()
+            .try_for_each(|statement| self.execute(statement.clone()));
+        self.finish(result)
+    }
+
+    // Like `interpret`, but a program that is a single expression statement also prints its value,
+    // so typing `1 + 2;` in the REPL shows `3`.
+    pub fn interpret_repl(&self, statements: Rc<Vec<Rc<Stmt>>>) -> bool {
+        let [statement] = statements.as_slice() else {
+            return self.interpret(statements);
+        };
+        let Stmt::Expression(stmt) = statement.as_ref() else {
+            return self.interpret(statements);
+        };
+
+        self.step(statement);
+        let result = self
+            .evaluate(stmt.expression.clone())
+            .and_then(|value| self.print(&value));
+        self.finish(result)
+    }
+
+    // Writes out the output and then reports the runtime error the program stopped at, if any, so
+    // the error shows up after what was printed before it. Other errors were reported when they
+    // were created.
+    fn finish(&self, result: Result<(), LoxResult>) -> bool {
+        let flushed = self.flush();
+        if let Err(e @ LoxResult::RuntimeError { .. }) = &result {
+            e.report("");
+        }
+        if let Err(e) = flushed {
+            LoxResult::system_error(&e.to_string());
+            return false;
+        }
+        result.is_ok()
+    }
+
+    fn print(&self, value: &Object) -> Result<(), LoxResult> {
+        let mut output = self.output.borrow_mut();
+        writeln!(output, "{}", value).map_err(|e| LoxResult::system_error(&e.to_string()))?;
+        if !self.config.buffered {
+            output.flush().map_err(|e| LoxResult::system_error(&e.to_string()))?;
         }
-        true
+        Ok(())
     }
 
     fn evaluate(&self, expr: Rc<Expr>) -> Result<Object, LoxResult> {
@@ -371,9 +664,19 @@ impl Interpreter {
     }
 
     fn execute(&self, statement: Rc<Stmt>) -> Result<(), LoxResult> {
+        self.step(&statement);
         statement.accept(statement.clone(), self)
     }
 
+    // Tells the step hook, if there is one, that `statement` is about to run. Whatever runs a
+    // statement without `execute` has to call this itself.
+    fn step(&self, statement: &Stmt) {
+        if let Some(hook) = self.step_hook.borrow_mut().as_mut() {
+            let (line, kind) = stmt_position(statement);
+            hook(line, kind);
+        }
+    }
+
     pub fn execute_block(
         &self,
         statements: &Rc<Vec<Rc<Stmt>>>,
@@ -385,7 +688,140 @@ impl Interpreter {
         result
     }
 
-    fn is_truthy(&self, object: &Object) -> bool {
+    // Runs one iteration of a loop with its own copy of the loop variables, so closures created
+    // in different iterations don't share one variable. The copies are written back afterwards,
+    // also after `break` or `continue`, so changes the body makes carry over.
+    fn execute_iteration(&self, names: &[Token], body: Rc<Stmt>) -> Result<(), LoxResult> {
+        let outer = self.environment.borrow().clone();
+        let mut env = Environment::new_with_enclosing(Rc::clone(&outer));
+        for name in names {
+            env.define(&name.lexeme, outer.borrow().get(name)?);
+        }
+        let env = Rc::new(RefCell::new(env));
+
+        let previous = self.environment.replace(Rc::clone(&env));
+        let result = self.execute(body);
+        self.environment.replace(previous);
+
+        for name in names {
+            let value = env.borrow().get_at(0, &name.lexeme);
+            outer.borrow_mut().assign(name, value)?;
+        }
+        result
+    }
+
+    // Field initializers are evaluated like a method body: in an environment where `this` is the
+    // new instance, enclosed by the environment the class was declared in.
+    pub fn initialize_fields(
+        &self,
+        fields: &Rc<Vec<Rc<Stmt>>>,
+        closure: &Rc<RefCell<Environment>>,
+        instance: &Rc<Instance>,
+    ) -> Result<(), LoxResult> {
+        let mut env = Environment::new_with_enclosing(Rc::clone(closure));
+        env.define("this", Object::Instance(Rc::clone(instance)));
+
+        let previous = self.environment.replace(Rc::new(RefCell::new(env)));
+        let result = fields.iter().try_for_each(|field| {
+            if let Stmt::Var(field) = field.deref() {
+                if let Some(initializer) = &field.initializer {
+                    let value = self.evaluate(initializer.clone())?;
+                    instance.set(&field.name, &value)?;
+                }
+            }
+            Ok(())
+        });
+        self.environment.replace(previous);
+        result
+    }
+
+    // Lets natives call back into functions, methods and classes. Failures are native errors, so
+    // they get reported at the call site of the native.
+    pub fn call_value(&self, callee: &Object, arguments: Vec<Object>) -> Result<Object, LoxResult> {
+        let Some((callfunc, klass)) = as_callable(callee) else {
+            return Err(LoxResult::native_error(&format!("'{}' is not callable.", callee)));
+        };
+
+        if let Some(message) = arity_error(callfunc.as_ref(), arguments.len()) {
+            return Err(LoxResult::native_error(&message));
+        }
+        self.check_deadline()?;
+        // the callback runs inside the native, so its frame gets the line the native was called on
+        let line = self.call_stack.borrow().last().map_or(0, |frame| frame.line);
+        self.call_in_frame(callee, line, callfunc, arguments, klass)
+    }
+
+    // Reading a getter calls it, so it's checked against the deadline and gets a frame on the
+    // line of the property like any other call.
+    pub(crate) fn call_getter(&self, getter: &Object, property: &Token) -> Result<Object, LoxResult> {
+        let Object::Func(func) = getter else {
+            unreachable!("a getter is a function");
+        };
+        self.check_deadline()?;
+        self.call_in_frame(getter, property.line, func.clone(), Vec::new(), None)
+    }
+
+    // Runs a call with a frame for it on the call stack. The frame is popped whether the call
+    // returned or failed, so the stack unwinds along with an error.
+    fn call_in_frame(
+        &self,
+        callee: &Object,
+        line: i32,
+        callfunc: Rc<dyn LoxCallable>,
+        arguments: Vec<Object>,
+        klass: Option<Rc<Class>>,
+    ) -> Result<Object, LoxResult> {
+        self.call_stack.borrow_mut().push(Frame {
+            name: callee_name(callee),
+            line,
+        });
+        let result = callfunc.call(self, arguments, klass);
+        self.call_stack.borrow_mut().pop();
+        result
+    }
+
+    // Runs `callee` but gives up once `timeout` has passed. Nothing can be interrupted from the
+    // outside, so the clock is only checked cooperatively: after every loop iteration and before
+    // every call. A callback stuck in a single long native call will overrun its budget. A
+    // timeout too long for the platform's clock to represent doesn't add a deadline.
+    pub fn call_with_timeout(&self, callee: &Object, timeout: Duration) -> Result<Object, LoxResult> {
+        let previous = self.deadline.get();
+        let deadline = match (previous, Instant::now().checked_add(timeout)) {
+            (Some(previous), Some(deadline)) => Some(previous.min(deadline)),
+            (previous, deadline) => previous.or(deadline),
+        };
+        self.deadline.set(deadline);
+
+        let result = self.call_value(callee, vec![]);
+        self.deadline.set(previous);
+        result
+    }
+
+    pub fn call_stack(&self) -> Vec<Frame> {
+        self.call_stack.borrow().clone()
+    }
+
+    // One line per unfinished call, the innermost one first.
+    pub fn backtrace(&self) -> String {
+        self.call_stack
+            .borrow()
+            .iter()
+            .rev()
+            .map(|frame| format!("  in {}() called on line {}", frame.name, frame.line))
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+
+    fn check_deadline(&self) -> Result<(), LoxResult> {
+        match self.deadline.get() {
+            Some(deadline) if Instant::now() > deadline => {
+                Err(LoxResult::native_error("Timed out."))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    pub fn is_truthy(&self, object: &Object) -> bool {
         !matches!(object, Object::Nil | Object::Bool(false))
     }
 
@@ -406,53 +842,177 @@ impl Interpreter {
     }
 }
 
+fn callee_name(callee: &Object) -> String {
+    match callee {
+        Object::Func(f) => f.name().to_string(),
+        Object::Native(n) => n.name.clone(),
+        Object::Class(c) => c.name().to_string(),
+        other => other.to_string(),
+    }
+}
+
+// The line a statement starts on and what kind of statement it is.
+fn stmt_position(stmt: &Stmt) -> (u32, &'static str) {
+    let (line, kind) = match stmt {
+        Stmt::Block(s) => (s.line, "block"),
+        Stmt::Break(s) => (s.keyword.line, "break"),
+        Stmt::Class(s) => (s.name.line, "class"),
+        Stmt::Continue(s) => (s.keyword.line, "continue"),
+        Stmt::Expression(s) => (s.line, "expression"),
+        Stmt::Function(s) => (s.name.line, "function"),
+        Stmt::If(s) => (s.line, "if"),
+        Stmt::Print(s) => (s.line, "print"),
+        Stmt::Return(s) => (s.keyword.line, "return"),
+        Stmt::Var(s) => (s.name.line, "var"),
+        Stmt::While(s) => (s.line, "while"),
+    };
+    (line as u32, kind)
+}
+
+// Checks that `index` can be used to index an array of length `len`.
+fn array_index(bracket: &Token, index: &Object, len: usize) -> Result<usize, LoxResult> {
+    let Object::Num(n) = *index else {
+        return Err(LoxResult::runtime_error(bracket, "Index must be a number."));
+    };
+    if n < 0.0 || n.fract() != 0.0 {
+        return Err(LoxResult::runtime_error(
+            bracket,
+            &format!("Index {} must be a non-negative integer.", n),
+        ));
+    }
+    if n as usize >= len {
+        return Err(LoxResult::runtime_error(
+            bracket,
+            &format!("Index {} is out of bounds for length {}.", n, len),
+        ));
+    }
+    Ok(n as usize)
+}
+
+fn map_key(token: &Token, key: &Object) -> Result<String, LoxResult> {
+    match key {
+        Object::Str(key) => Ok(key.clone()),
+        other => Err(LoxResult::runtime_error(
+            token,
+            &format!("Map key '{}' is not a string.", other),
+        )),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::parser::Parser;
+    use crate::resolver::Resolver;
+    use crate::scanner::Scanner;
+    use crate::test_utils::SharedBuffer;
     use crate::token::Token;
 
+    fn try_run_program(source: &str) -> (Interpreter, bool) {
+        try_run_program_with(source, |_| {})
+    }
+
+    // Like `try_run_program`, but `setup` gets to configure the interpreter first.
+    fn try_run_program_with(source: &str, setup: impl FnOnce(&Interpreter)) -> (Interpreter, bool) {
+        let interpreter = Interpreter::new();
+        setup(&interpreter);
+        let tokens = Scanner::new(source).tokenize().unwrap();
+        let statements = Rc::new(Parser::new(tokens).parse().unwrap());
+        {
+            let resolver = Resolver::new(&interpreter);
+            resolver.resolve(Rc::clone(&statements)).unwrap();
+            assert!(resolver.success());
+        }
+        let success = interpreter.interpret(statements);
+        (interpreter, success)
+    }
+
+    fn run_program(source: &str) -> Interpreter {
+        let (interpreter, success) = try_run_program(source);
+        assert!(success);
+        interpreter
+    }
+
+    fn program_output(source: &str, buffered: bool) -> String {
+        captured_output(source, buffered, Interpreter::interpret)
+    }
+
+    fn repl_output(source: &str) -> String {
+        captured_output(source, false, Interpreter::interpret_repl)
+    }
+
+    fn captured_output(
+        source: &str,
+        buffered: bool,
+        interpret: fn(&Interpreter, Rc<Vec<Rc<Stmt>>>) -> bool,
+    ) -> String {
+        let buffer = SharedBuffer::default();
+        let interpreter = Interpreter::new()
+            .with_output(Box::new(buffer.clone()))
+            .buffered(buffered);
+
+        let tokens = Scanner::new(source).tokenize().unwrap();
+        let statements = Rc::new(Parser::new(tokens).parse().unwrap());
+        Resolver::new(&interpreter).resolve(Rc::clone(&statements)).unwrap();
+        assert!(interpret(&interpreter, statements));
+
+        // everything has to be written out once `interpret` returns, before the REPL reads the
+        // next line of input
+        let output = String::from_utf8(buffer.0.borrow().clone()).unwrap();
+        output
+    }
+
+    fn global(interpreter: &Interpreter, name: &str) -> Object {
+        let token = Token::new(TokenType::Identifier, name.to_string(), None, 1, 0);
+        interpreter.globals.borrow().get(&token).unwrap()
+    }
+
     fn minus() -> Token {
-        Token::new(TokenType::Minus, "-".to_string(), None, 1)
+        Token::new(TokenType::Minus, "-".to_string(), None, 1, 0)
+    }
+
+    fn percent() -> Token {
+        Token::new(TokenType::Percent, "%".to_string(), None, 1, 0)
     }
 
     fn plus() -> Token {
-        Token::new(TokenType::Plus, "+".to_string(), None, 1)
+        Token::new(TokenType::Plus, "+".to_string(), None, 1, 0)
     }
 
     fn star() -> Token {
-        Token::new(TokenType::Star, "*".to_string(), None, 1)
+        Token::new(TokenType::Star, "*".to_string(), None, 1, 0)
     }
 
     fn slash() -> Token {
-        Token::new(TokenType::Slash, "/".to_string(), None, 1)
+        Token::new(TokenType::Slash, "/".to_string(), None, 1, 0)
     }
 
     fn bang() -> Token {
-        Token::new(TokenType::Bang, "!".to_string(), None, 1)
+        Token::new(TokenType::Bang, "!".to_string(), None, 1, 0)
     }
 
     fn greater() -> Token {
-        Token::new(TokenType::Greater, ">".to_string(), None, 1)
+        Token::new(TokenType::Greater, ">".to_string(), None, 1, 0)
     }
 
     fn greater_equal() -> Token {
-        Token::new(TokenType::GreaterEqual, ">=".to_string(), None, 1)
+        Token::new(TokenType::GreaterEqual, ">=".to_string(), None, 1, 0)
     }
 
     fn less() -> Token {
-        Token::new(TokenType::Less, "<".to_string(), None, 1)
+        Token::new(TokenType::Less, "<".to_string(), None, 1, 0)
     }
 
     fn less_equal() -> Token {
-        Token::new(TokenType::LessEqual, "<=".to_string(), None, 1)
+        Token::new(TokenType::LessEqual, "<=".to_string(), None, 1, 0)
     }
 
     fn bang_equal() -> Token {
-        Token::new(TokenType::BangEqual, "!=".to_string(), None, 1)
+        Token::new(TokenType::BangEqual, "!=".to_string(), None, 1, 0)
     }
 
     fn equal_equal() -> Token {
-        Token::new(TokenType::EqualEqual, "==".to_string(), None, 1)
+        Token::new(TokenType::EqualEqual, "==".to_string(), None, 1, 0)
     }
 
     fn number(n: i32) -> Object {
@@ -471,427 +1031,483 @@ mod tests {
         Object::Nil
     }
 
-    fn run(expr: &Expr) -> Result<Object, LoxResult> {
+    fn run(expr: Expr) -> Result<Object, LoxResult> {
         let interpreter = Interpreter::new();
-        interpreter.evaluate(expr)
+        interpreter.evaluate(Rc::new(expr))
     }
 
     #[test]
     fn unary_number() {
-        let expr = Expr::Unary(UnaryExpr {
+        let expr = Expr::Unary(Rc::new(UnaryExpr {
             operator: minus(),
-            right: Box::new(Expr::Literal(LiteralExpr {
+            right: Rc::new(Expr::Literal(Rc::new(LiteralExpr {
                 value: Some(number(10)),
-            })),
-        });
+            }))),
+        }));
         let expected = number(-10);
-        let res = run(&expr).unwrap();
+        let res = run(expr).unwrap();
 
         assert_eq!(expected, res);
     }
 
     #[test]
     fn unary_double_number() {
-        let expr = Expr::Unary(UnaryExpr {
+        let expr = Expr::Unary(Rc::new(UnaryExpr {
             operator: minus(),
-            right: Box::new(Expr::Unary(UnaryExpr {
+            right: Rc::new(Expr::Unary(Rc::new(UnaryExpr {
                 operator: minus(),
-                right: Box::new(Expr::Literal(LiteralExpr {
+                right: Rc::new(Expr::Literal(Rc::new(LiteralExpr {
                     value: Some(number(10)),
-                })),
-            })),
-        });
+                }))),
+            }))),
+        }));
         let expected = number(10);
-        let res = run(&expr).unwrap();
+        let res = run(expr).unwrap();
         assert_eq!(expected, res);
     }
 
     #[test]
     fn binary_star() {
-        let expr = Expr::Binary(BinaryExpr {
-            left: Box::new(Expr::Literal(LiteralExpr {
+        let expr = Expr::Binary(Rc::new(BinaryExpr {
+            left: Rc::new(Expr::Literal(Rc::new(LiteralExpr {
                 value: Some(number(10)),
-            })),
+            }))),
             operator: star(),
-            right: Box::new(Expr::Literal(LiteralExpr {
+            right: Rc::new(Expr::Literal(Rc::new(LiteralExpr {
                 value: Some(number(10)),
-            })),
-        });
+            }))),
+        }));
         let expected = number(100);
-        let res = run(&expr).unwrap();
+        let res = run(expr).unwrap();
         assert_eq!(expected, res);
     }
 
     #[test]
     fn binary_minus() {
-        let expr = Expr::Binary(BinaryExpr {
-            left: Box::new(Expr::Literal(LiteralExpr {
+        let expr = Expr::Binary(Rc::new(BinaryExpr {
+            left: Rc::new(Expr::Literal(Rc::new(LiteralExpr {
                 value: Some(number(10)),
-            })),
+            }))),
             operator: minus(),
-            right: Box::new(Expr::Literal(LiteralExpr {
+            right: Rc::new(Expr::Literal(Rc::new(LiteralExpr {
                 value: Some(number(5)),
-            })),
-        });
+            }))),
+        }));
         let expected = number(5);
-        let res = run(&expr).unwrap();
+        let res = run(expr).unwrap();
         assert_eq!(expected, res);
     }
 
     #[test]
     fn binary_slash() {
-        let expr = Expr::Binary(BinaryExpr {
-            left: Box::new(Expr::Literal(LiteralExpr {
+        let expr = Expr::Binary(Rc::new(BinaryExpr {
+            left: Rc::new(Expr::Literal(Rc::new(LiteralExpr {
                 value: Some(number(10)),
-            })),
+            }))),
             operator: slash(),
-            right: Box::new(Expr::Literal(LiteralExpr {
+            right: Rc::new(Expr::Literal(Rc::new(LiteralExpr {
                 value: Some(number(5)),
-            })),
-        });
+            }))),
+        }));
         let expected = number(2);
-        let res = run(&expr).unwrap();
+        let res = run(expr).unwrap();
+        assert_eq!(expected, res);
+    }
+
+    #[test]
+    fn binary_percent() {
+        let expr = Expr::Binary(Rc::new(BinaryExpr {
+            left: Rc::new(Expr::Literal(Rc::new(LiteralExpr {
+                value: Some(number(7)),
+            }))),
+            operator: percent(),
+            right: Rc::new(Expr::Literal(Rc::new(LiteralExpr {
+                value: Some(number(3)),
+            }))),
+        }));
+        let expected = number(1);
+        let res = run(expr).unwrap();
+        assert_eq!(expected, res);
+    }
+
+    #[test]
+    fn binary_percent_negative_dividend() {
+        let expr = Expr::Binary(Rc::new(BinaryExpr {
+            left: Rc::new(Expr::Literal(Rc::new(LiteralExpr {
+                value: Some(number(-1)),
+            }))),
+            operator: percent(),
+            right: Rc::new(Expr::Literal(Rc::new(LiteralExpr {
+                value: Some(number(3)),
+            }))),
+        }));
+        let expected = number(-1);
+        let res = run(expr).unwrap();
         assert_eq!(expected, res);
     }
 
+    #[test]
+    fn binary_percent_by_zero() {
+        let expr = Expr::Binary(Rc::new(BinaryExpr {
+            left: Rc::new(Expr::Literal(Rc::new(LiteralExpr {
+                value: Some(number(7)),
+            }))),
+            operator: percent(),
+            right: Rc::new(Expr::Literal(Rc::new(LiteralExpr {
+                value: Some(number(0)),
+            }))),
+        }));
+        assert!(run(expr).is_err());
+    }
+
+    #[test]
+    fn percent_binds_like_star() {
+        let interpreter = run_program("var a = 1 + 7 % 4 * 2; var b = 10 - 7 % 3;");
+        assert_eq!(global(&interpreter, "a"), number(7));
+        assert_eq!(global(&interpreter, "b"), number(9));
+    }
+
     #[test]
     fn binary_plus() {
-        let expr = Expr::Binary(BinaryExpr {
-            left: Box::new(Expr::Literal(LiteralExpr {
+        let expr = Expr::Binary(Rc::new(BinaryExpr {
+            left: Rc::new(Expr::Literal(Rc::new(LiteralExpr {
                 value: Some(number(10)),
-            })),
+            }))),
             operator: plus(),
-            right: Box::new(Expr::Literal(LiteralExpr {
+            right: Rc::new(Expr::Literal(Rc::new(LiteralExpr {
                 value: Some(number(5)),
-            })),
-        });
+            }))),
+        }));
         let expected = number(15);
-        let res = run(&expr).unwrap();
+        let res = run(expr).unwrap();
         assert_eq!(expected, res);
     }
 
     #[test]
     fn binary_plus_str() {
-        let expr = Expr::Binary(BinaryExpr {
-            left: Box::new(Expr::Literal(LiteralExpr {
+        let expr = Expr::Binary(Rc::new(BinaryExpr {
+            left: Rc::new(Expr::Literal(Rc::new(LiteralExpr {
                 value: Some(string("hello")),
-            })),
+            }))),
             operator: plus(),
-            right: Box::new(Expr::Literal(LiteralExpr {
+            right: Rc::new(Expr::Literal(Rc::new(LiteralExpr {
                 value: Some(string(" world!")),
-            })),
-        });
+            }))),
+        }));
         let expected = string("hello world!");
-        let res = run(&expr);
+        let res = run(expr);
         assert_eq!(expected, res.unwrap());
     }
 
     #[test]
     fn binary_arithmetic_error() {
-        let expr = Expr::Binary(BinaryExpr {
-            left: Box::new(Expr::Literal(LiteralExpr {
+        let expr = Expr::Binary(Rc::new(BinaryExpr {
+            left: Rc::new(Expr::Literal(Rc::new(LiteralExpr {
                 value: Some(string("hello")),
-            })),
-            operator: plus(),
-            right: Box::new(Expr::Literal(LiteralExpr {
+            }))),
+            // `+` concatenates a string and a number, `-` has no meaning for them
+            operator: minus(),
+            right: Rc::new(Expr::Literal(Rc::new(LiteralExpr {
                 value: Some(number(3)),
-            })),
-        });
+            }))),
+        }));
         let expected = Object::ArithmeticError;
-        assert!(run(&expr).is_err());
+        assert!(run(expr).is_err());
     }
 
     #[test]
     fn unary_boolean() {
-        let expr = Expr::Unary(UnaryExpr {
+        let expr = Expr::Unary(Rc::new(UnaryExpr {
             operator: bang(),
-            right: Box::new(Expr::Literal(LiteralExpr {
+            right: Rc::new(Expr::Literal(Rc::new(LiteralExpr {
                 value: Some(Object::Bool(true)),
-            })),
-        });
+            }))),
+        }));
         let expected = Object::Bool(false);
-        let res = run(&expr).unwrap();
+        let res = run(expr).unwrap();
 
         assert_eq!(expected, res);
     }
 
     #[test]
     fn unary_double_boolean() {
-        let expr = Expr::Unary(UnaryExpr {
+        let expr = Expr::Unary(Rc::new(UnaryExpr {
             operator: bang(),
-            right: Box::new(Expr::Unary(UnaryExpr {
+            right: Rc::new(Expr::Unary(Rc::new(UnaryExpr {
                 operator: bang(),
-                right: Box::new(Expr::Literal(LiteralExpr {
+                right: Rc::new(Expr::Literal(Rc::new(LiteralExpr {
                     value: Some(Object::Bool(true)),
-                })),
-            })),
-        });
+                }))),
+            }))),
+        }));
         let expected = Object::Bool(true);
-        let res = run(&expr).unwrap();
+        let res = run(expr).unwrap();
         assert_eq!(expected, res);
     }
 
     #[test]
     fn binary_greater() {
-        let expr = Expr::Binary(BinaryExpr {
-            left: Box::new(Expr::Literal(LiteralExpr {
+        let expr = Expr::Binary(Rc::new(BinaryExpr {
+            left: Rc::new(Expr::Literal(Rc::new(LiteralExpr {
                 value: Some(number(10)),
-            })),
+            }))),
             operator: greater(),
-            right: Box::new(Expr::Literal(LiteralExpr {
+            right: Rc::new(Expr::Literal(Rc::new(LiteralExpr {
                 value: Some(number(5)),
-            })),
-        });
+            }))),
+        }));
         let expected = boolean(true);
-        let res = run(&expr);
+        let res = run(expr);
         assert_eq!(expected, res.unwrap());
     }
 
     #[test]
     fn binary_greater_fail() {
-        let expr = Expr::Binary(BinaryExpr {
-            left: Box::new(Expr::Literal(LiteralExpr {
+        let expr = Expr::Binary(Rc::new(BinaryExpr {
+            left: Rc::new(Expr::Literal(Rc::new(LiteralExpr {
                 value: Some(number(10)),
-            })),
+            }))),
             operator: greater(),
-            right: Box::new(Expr::Literal(LiteralExpr {
+            right: Rc::new(Expr::Literal(Rc::new(LiteralExpr {
                 value: Some(number(10)),
-            })),
-        });
+            }))),
+        }));
         let expected = boolean(false);
-        let res = run(&expr);
+        let res = run(expr);
         assert_eq!(expected, res.unwrap());
     }
 
     #[test]
     fn binary_greater_equal() {
-        let expr = Expr::Binary(BinaryExpr {
-            left: Box::new(Expr::Literal(LiteralExpr {
+        let expr = Expr::Binary(Rc::new(BinaryExpr {
+            left: Rc::new(Expr::Literal(Rc::new(LiteralExpr {
                 value: Some(number(10)),
-            })),
+            }))),
             operator: greater_equal(),
-            right: Box::new(Expr::Literal(LiteralExpr {
+            right: Rc::new(Expr::Literal(Rc::new(LiteralExpr {
                 value: Some(number(10)),
-            })),
-        });
+            }))),
+        }));
         let expected = boolean(true);
-        let res = run(&expr);
+        let res = run(expr);
         assert_eq!(expected, res.unwrap());
     }
 
     #[test]
     fn binary_greater_equal_fail() {
-        let expr = Expr::Binary(BinaryExpr {
-            left: Box::new(Expr::Literal(LiteralExpr {
+        let expr = Expr::Binary(Rc::new(BinaryExpr {
+            left: Rc::new(Expr::Literal(Rc::new(LiteralExpr {
                 value: Some(number(10)),
-            })),
+            }))),
             operator: greater_equal(),
-            right: Box::new(Expr::Literal(LiteralExpr {
+            right: Rc::new(Expr::Literal(Rc::new(LiteralExpr {
                 value: Some(number(11)),
-            })),
-        });
+            }))),
+        }));
         let expected = boolean(false);
-        let res = run(&expr);
+        let res = run(expr);
         assert_eq!(expected, res.unwrap());
     }
 
     #[test]
     fn binary_less_fail() {
-        let expr = Expr::Binary(BinaryExpr {
-            left: Box::new(Expr::Literal(LiteralExpr {
+        let expr = Expr::Binary(Rc::new(BinaryExpr {
+            left: Rc::new(Expr::Literal(Rc::new(LiteralExpr {
                 value: Some(number(10)),
-            })),
+            }))),
             operator: less(),
-            right: Box::new(Expr::Literal(LiteralExpr {
+            right: Rc::new(Expr::Literal(Rc::new(LiteralExpr {
                 value: Some(number(5)),
-            })),
-        });
+            }))),
+        }));
         let expected = boolean(false);
-        let res = run(&expr);
+        let res = run(expr);
         assert_eq!(expected, res.unwrap());
     }
 
     #[test]
     fn binary_less() {
-        let expr = Expr::Binary(BinaryExpr {
-            left: Box::new(Expr::Literal(LiteralExpr {
+        let expr = Expr::Binary(Rc::new(BinaryExpr {
+            left: Rc::new(Expr::Literal(Rc::new(LiteralExpr {
                 value: Some(number(10)),
-            })),
+            }))),
             operator: less(),
-            right: Box::new(Expr::Literal(LiteralExpr {
+            right: Rc::new(Expr::Literal(Rc::new(LiteralExpr {
                 value: Some(number(10)),
-            })),
-        });
+            }))),
+        }));
         let expected = boolean(false);
-        let res = run(&expr);
+        let res = run(expr);
         assert_eq!(expected, res.unwrap());
     }
 
     #[test]
     fn binary_less_equal() {
-        let expr = Expr::Binary(BinaryExpr {
-            left: Box::new(Expr::Literal(LiteralExpr {
+        let expr = Expr::Binary(Rc::new(BinaryExpr {
+            left: Rc::new(Expr::Literal(Rc::new(LiteralExpr {
                 value: Some(number(10)),
-            })),
+            }))),
             operator: less_equal(),
-            right: Box::new(Expr::Literal(LiteralExpr {
+            right: Rc::new(Expr::Literal(Rc::new(LiteralExpr {
                 value: Some(number(10)),
-            })),
-        });
+            }))),
+        }));
         let expected = boolean(true);
-        let res = run(&expr);
+        let res = run(expr);
         assert_eq!(expected, res.unwrap());
     }
 
     #[test]
     fn binary_less_equal_fail() {
-        let expr = Expr::Binary(BinaryExpr {
-            left: Box::new(Expr::Literal(LiteralExpr {
+        let expr = Expr::Binary(Rc::new(BinaryExpr {
+            left: Rc::new(Expr::Literal(Rc::new(LiteralExpr {
                 value: Some(number(11)),
-            })),
+            }))),
             operator: less_equal(),
-            right: Box::new(Expr::Literal(LiteralExpr {
+            right: Rc::new(Expr::Literal(Rc::new(LiteralExpr {
                 value: Some(number(10)),
-            })),
-        });
+            }))),
+        }));
         let expected = boolean(false);
-        let res = run(&expr);
+        let res = run(expr);
         assert_eq!(expected, res.unwrap());
     }
 
     #[test]
     fn binary_bang_equal_fail() {
-        let expr = Expr::Binary(BinaryExpr {
-            left: Box::new(Expr::Literal(LiteralExpr {
+        let expr = Expr::Binary(Rc::new(BinaryExpr {
+            left: Rc::new(Expr::Literal(Rc::new(LiteralExpr {
                 value: Some(number(11)),
-            })),
+            }))),
             operator: bang_equal(),
-            right: Box::new(Expr::Literal(LiteralExpr {
+            right: Rc::new(Expr::Literal(Rc::new(LiteralExpr {
                 value: Some(number(11)),
-            })),
-        });
+            }))),
+        }));
         let expected = boolean(false);
-        let res = run(&expr);
+        let res = run(expr);
         assert_eq!(expected, res.unwrap());
     }
 
     #[test]
     fn binary_bang_equal() {
-        let expr = Expr::Binary(BinaryExpr {
-            left: Box::new(Expr::Literal(LiteralExpr {
+        let expr = Expr::Binary(Rc::new(BinaryExpr {
+            left: Rc::new(Expr::Literal(Rc::new(LiteralExpr {
                 value: Some(number(11)),
-            })),
+            }))),
             operator: bang_equal(),
-            right: Box::new(Expr::Literal(LiteralExpr {
+            right: Rc::new(Expr::Literal(Rc::new(LiteralExpr {
                 value: Some(number(10)),
-            })),
-        });
+            }))),
+        }));
         let expected = boolean(true);
-        let res = run(&expr);
+        let res = run(expr);
         assert_eq!(expected, res.unwrap());
     }
 
     #[test]
     fn binary_equal_equal_fail() {
-        let expr = Expr::Binary(BinaryExpr {
-            left: Box::new(Expr::Literal(LiteralExpr {
+        let expr = Expr::Binary(Rc::new(BinaryExpr {
+            left: Rc::new(Expr::Literal(Rc::new(LiteralExpr {
                 value: Some(number(11)),
-            })),
+            }))),
             operator: equal_equal(),
-            right: Box::new(Expr::Literal(LiteralExpr {
+            right: Rc::new(Expr::Literal(Rc::new(LiteralExpr {
                 value: Some(number(10)),
-            })),
-        });
+            }))),
+        }));
         let expected = boolean(false);
-        let res = run(&expr);
+        let res = run(expr);
         assert_eq!(expected, res.unwrap());
     }
 
     #[test]
     fn binary_equal_equal() {
-        let expr = Expr::Binary(BinaryExpr {
-            left: Box::new(Expr::Literal(LiteralExpr {
+        let expr = Expr::Binary(Rc::new(BinaryExpr {
+            left: Rc::new(Expr::Literal(Rc::new(LiteralExpr {
                 value: Some(number(11)),
-            })),
+            }))),
             operator: equal_equal(),
-            right: Box::new(Expr::Literal(LiteralExpr {
+            right: Rc::new(Expr::Literal(Rc::new(LiteralExpr {
                 value: Some(number(11)),
-            })),
-        });
+            }))),
+        }));
         let expected = boolean(true);
-        let res = run(&expr);
+        let res = run(expr);
         assert_eq!(expected, res.unwrap());
     }
 
     #[test]
     fn binary_equal_equal_nil() {
-        let expr = Expr::Binary(BinaryExpr {
-            left: Box::new(Expr::Literal(LiteralExpr { value: Some(nil()) })),
+        let expr = Expr::Binary(Rc::new(BinaryExpr {
+            left: Rc::new(Expr::Literal(Rc::new(LiteralExpr { value: Some(nil()) }))),
             operator: equal_equal(),
-            right: Box::new(Expr::Literal(LiteralExpr { value: Some(nil()) })),
-        });
+            right: Rc::new(Expr::Literal(Rc::new(LiteralExpr { value: Some(nil()) }))),
+        }));
         let expected = boolean(true);
-        let res = run(&expr);
+        let res = run(expr);
         assert_eq!(expected, res.unwrap());
     }
 
     #[test]
     fn binary_equal_equal_nil_fail() {
-        let expr = Expr::Binary(BinaryExpr {
-            left: Box::new(Expr::Literal(LiteralExpr {
+        let expr = Expr::Binary(Rc::new(BinaryExpr {
+            left: Rc::new(Expr::Literal(Rc::new(LiteralExpr {
                 value: Some(number(4)),
-            })),
+            }))),
             operator: equal_equal(),
-            right: Box::new(Expr::Literal(LiteralExpr { value: Some(nil()) })),
-        });
+            right: Rc::new(Expr::Literal(Rc::new(LiteralExpr { value: Some(nil()) }))),
+        }));
         let expected = boolean(false);
-        let res = run(&expr);
+        let res = run(expr);
         assert_eq!(expected, res.unwrap());
     }
 
     #[test]
     fn binary_equal_equal_string() {
-        let expr = Expr::Binary(BinaryExpr {
-            left: Box::new(Expr::Literal(LiteralExpr {
+        let expr = Expr::Binary(Rc::new(BinaryExpr {
+            left: Rc::new(Expr::Literal(Rc::new(LiteralExpr {
                 value: Some(string("hello")),
-            })),
+            }))),
             operator: equal_equal(),
-            right: Box::new(Expr::Literal(LiteralExpr {
+            right: Rc::new(Expr::Literal(Rc::new(LiteralExpr {
                 value: Some(string("hello")),
-            })),
-        });
+            }))),
+        }));
         let expected = boolean(true);
-        let res = run(&expr);
+        let res = run(expr);
         assert_eq!(expected, res.unwrap());
     }
 
     #[test]
     fn binary_equal_equal_string_fail() {
-        let expr = Expr::Binary(BinaryExpr {
-            left: Box::new(Expr::Literal(LiteralExpr {
+        let expr = Expr::Binary(Rc::new(BinaryExpr {
+            left: Rc::new(Expr::Literal(Rc::new(LiteralExpr {
                 value: Some(string("ello")),
-            })),
+            }))),
             operator: equal_equal(),
-            right: Box::new(Expr::Literal(LiteralExpr {
+            right: Rc::new(Expr::Literal(Rc::new(LiteralExpr {
                 value: Some(string("hello")),
-            })),
-        });
+            }))),
+        }));
         let expected = boolean(false);
-        let res = run(&expr);
+        let res = run(expr);
         assert_eq!(expected, res.unwrap());
     }
 
     #[test]
     fn test_var_stmt() {
         let interpreter = Interpreter::new();
-        let name = Token::new(TokenType::Identifier, "foo".to_string(), None, 1);
+        let name = Token::new(TokenType::Identifier, "foo".to_string(), None, 1, 0);
         let var_stmt = VarStmt {
             name: name.clone(),
-            initializer: Some(Expr::Literal(LiteralExpr {
+            initializer: Some(Rc::new(Expr::Literal(Rc::new(LiteralExpr {
                 value: Some(number(10)),
-            })),
+            })))),
         };
-        assert!(interpreter.visit_var_stmt(&var_stmt).is_ok());
+        let var_stmt = Rc::new(var_stmt);
+        let wrapper = Rc::new(Stmt::Var(Rc::clone(&var_stmt)));
+        assert!(interpreter.visit_var_stmt(wrapper, &var_stmt).is_ok());
         assert_eq!(
             interpreter
                 .environment
@@ -906,12 +1522,14 @@ mod tests {
     #[test]
     fn test_var_stmt_undefined() {
         let interpreter = Interpreter::new();
-        let name = Token::new(TokenType::Identifier, "foo".to_string(), None, 1);
+        let name = Token::new(TokenType::Identifier, "foo".to_string(), None, 1, 0);
         let var_stmt = VarStmt {
             name: name.clone(),
             initializer: None,
         };
-        assert!(interpreter.visit_var_stmt(&var_stmt).is_ok());
+        let var_stmt = Rc::new(var_stmt);
+        let wrapper = Rc::new(Stmt::Var(Rc::clone(&var_stmt)));
+        assert!(interpreter.visit_var_stmt(wrapper, &var_stmt).is_ok());
         assert_eq!(
             interpreter
                 .environment
@@ -926,17 +1544,20 @@ mod tests {
     #[test]
     fn test_var_expr() {
         let interpreter = Interpreter::new();
-        let name = Token::new(TokenType::Identifier, "foo".to_string(), None, 1);
+        let name = Token::new(TokenType::Identifier, "foo".to_string(), None, 1, 0);
         let var_stmt = VarStmt {
             name: name.clone(),
-            initializer: Some(Expr::Literal(LiteralExpr {
+            initializer: Some(Rc::new(Expr::Literal(Rc::new(LiteralExpr {
                 value: Some(number(10)),
-            })),
+            })))),
         };
-        assert!(interpreter.visit_var_stmt(&var_stmt).is_ok());
-        let var_expression = VariableExpr { name: name.clone() };
+        let var_stmt = Rc::new(var_stmt);
+        let wrapper = Rc::new(Stmt::Var(Rc::clone(&var_stmt)));
+        assert!(interpreter.visit_var_stmt(wrapper, &var_stmt).is_ok());
+        let var_expression = Rc::new(VariableExpr { name: name.clone() });
+        let wrapper = Rc::new(Expr::Variable(Rc::clone(&var_expression)));
         assert_eq!(
-            interpreter.visit_variable_expr(&var_expression).unwrap(),
+            interpreter.visit_variable_expr(wrapper, &var_expression).unwrap(),
             Object::Num(10.0)
         );
     }
@@ -944,25 +1565,1153 @@ mod tests {
     #[test]
     fn test_var_expr_undefined() {
         let interpreter = Interpreter::new();
-        let name = Token::new(TokenType::Identifier, "foo".to_string(), None, 1);
-        let var_expression = VariableExpr { name: name.clone() };
-        assert!(interpreter.visit_variable_expr(&var_expression).is_err());
+        let name = Token::new(TokenType::Identifier, "foo".to_string(), None, 1, 0);
+        let var_expression = Rc::new(VariableExpr { name: name.clone() });
+        let wrapper = Rc::new(Expr::Variable(Rc::clone(&var_expression)));
+        assert!(interpreter.visit_variable_expr(wrapper, &var_expression).is_err());
     }
 
     #[test]
     fn assign_value_to_variable_undefined() {
         let mut e = Environment::new();
-        let name = Token::new(TokenType::Identifier, "foo".to_string(), None, 1);
+        let name = Token::new(TokenType::Identifier, "foo".to_string(), None, 1, 0);
         assert!(e.assign(&name, Object::Nil).is_err());
     }
 
     #[test]
     fn reassign_value_to_existing_variable() {
         let mut e = Environment::new();
-        let id = Token::new(TokenType::Identifier, "foo".to_string(), None, 1);
-        e.define(&"foo".to_string(), Object::Num(10.0));
+        let id = Token::new(TokenType::Identifier, "foo".to_string(), None, 1, 0);
+        e.define("foo", Object::Num(10.0));
         assert_eq!(e.get(&id).unwrap(), Object::Num(10.0));
         assert!(e.assign(&id, Object::Num(100.0)).is_ok());
         assert_eq!(e.get(&id).unwrap(), Object::Num(100.0));
     }
+
+    #[test]
+    fn class_field_defaults() {
+        let interpreter = run_program(
+            "class Point { x = 1; y = this.x + 1; }
+             var p = Point();
+             var x = p.x;
+             var y = p.y;",
+        );
+        assert_eq!(global(&interpreter, "x"), number(1));
+        assert_eq!(global(&interpreter, "y"), number(2));
+    }
+
+    #[test]
+    fn class_field_defaults_overridden_by_init() {
+        let interpreter = run_program(
+            "class Base { x = 0; y = 0; }
+             class Point < Base {
+                 y = 5;
+                 init(x) { this.x = x; }
+             }
+             var p = Point(3);
+             var x = p.x;
+             var y = p.y;",
+        );
+        assert_eq!(global(&interpreter, "x"), number(3));
+        assert_eq!(global(&interpreter, "y"), number(5));
+    }
+
+    #[test]
+    fn object_id_is_identity() {
+        let interpreter = run_program(
+            "class A {}
+             var a = A();
+             var b = A();
+             var first = object_id(a);
+             var second = object_id(a);
+             var other = object_id(b);",
+        );
+        assert_eq!(global(&interpreter, "first"), global(&interpreter, "second"));
+        assert_ne!(global(&interpreter, "first"), global(&interpreter, "other"));
+    }
+
+    #[test]
+    fn buffered_output_matches_unbuffered() {
+        let source = "for (var i = 0; i < 3; i = i + 1) { print i; }
+                      print \"done\";";
+
+        assert_eq!(program_output(source, false), "0\n1\n2\ndone\n");
+        assert_eq!(program_output(source, true), program_output(source, false));
+    }
+
+    #[test]
+    fn buffered_output_is_written_on_flush() {
+        let buffer = SharedBuffer::default();
+        let interpreter = Interpreter::new()
+            .with_output(Box::new(buffer.clone()))
+            .buffered(true);
+
+        let tokens = Scanner::new("print 1;").tokenize().unwrap();
+        let statements = Parser::new(tokens).parse().unwrap();
+        interpreter.execute(statements[0].clone()).unwrap();
+        assert!(buffer.0.borrow().is_empty());
+
+        interpreter.flush().unwrap();
+        assert_eq!(buffer.0.borrow().as_slice(), b"1\n");
+    }
+
+    #[test]
+    fn buffered_output_is_written_when_a_runtime_error_stops_the_program() {
+        let buffer = SharedBuffer::default();
+        let interpreter = Interpreter::new()
+            .with_output(Box::new(buffer.clone()))
+            .buffered(true);
+
+        let tokens = Scanner::new("print 1; nil + 1; print 2;").tokenize().unwrap();
+        let statements = Parser::new(tokens).parse().unwrap();
+
+        assert!(!interpreter.interpret(Rc::new(statements)));
+        assert_eq!(buffer.0.borrow().as_slice(), b"1\n");
+    }
+
+    #[test]
+    fn with_config_buffers_output() {
+        let buffer = SharedBuffer::default();
+        let config = Config {
+            buffered: true,
+            ..Config::default()
+        };
+        let interpreter = Interpreter::with_config(config).with_output(Box::new(buffer.clone()));
+
+        let tokens = Scanner::new("print 1;").tokenize().unwrap();
+        let statements = Parser::new(tokens).parse().unwrap();
+        interpreter.execute(statements[0].clone()).unwrap();
+        assert!(buffer.0.borrow().is_empty());
+
+        let unbuffered = Interpreter::new().with_output(Box::new(buffer.clone()));
+        unbuffered.execute(statements[0].clone()).unwrap();
+        assert_eq!(buffer.0.borrow().as_slice(), b"1\n");
+    }
+
+    #[test]
+    fn with_timeout_returns_result_in_time() {
+        let interpreter = run_program(
+            "fun quick() { return 42; }
+             var result = with_timeout(quick, 1000);",
+        );
+        assert_eq!(global(&interpreter, "result"), number(42));
+    }
+
+    #[test]
+    fn with_timeout_longer_than_the_clock_has_no_deadline() {
+        let interpreter = run_program("fun quick() { return 42; }");
+        let quick = global(&interpreter, "quick");
+        assert_eq!(interpreter.call_with_timeout(&quick, Duration::MAX).unwrap(), number(42));
+        assert!(interpreter.deadline.get().is_none());
+    }
+
+    #[test]
+    fn with_timeout_stops_long_loop() {
+        let (interpreter, success) = try_run_program(
+            "var spins = 0;
+             fun forever() { while (true) { spins = spins + 1; } }
+             with_timeout(forever, 10);
+             var after = true;",
+        );
+        assert!(!success);
+        assert!(interpreter.deadline.get().is_none());
+        assert!(interpreter
+            .globals
+            .borrow()
+            .get(&Token::new(TokenType::Identifier, "after".to_string(), None, 1, 0))
+            .is_err());
+    }
+
+    #[test]
+    fn natives_print_their_registered_name() {
+        assert_eq!(
+            program_output("print clock; print num_to_str;", false),
+            "<native fn clock>\n<native fn num_to_str>\n"
+        );
+    }
+
+    #[test]
+    fn assign_fields_merges_instances() {
+        let interpreter = run_program(
+            "class Point {}
+             var target = Point();
+             target.x = 1;
+             target.y = 1;
+             var source = Point();
+             source.y = 2;
+             source.z = 3;
+             var result = assign_fields(target, source);
+             var x = target.x;
+             var y = target.y;
+             var z = target.z;
+             var same = result == target;",
+        );
+        assert_eq!(global(&interpreter, "x"), number(1));
+        assert_eq!(global(&interpreter, "y"), number(2));
+        assert_eq!(global(&interpreter, "z"), number(3));
+        assert_eq!(global(&interpreter, "same"), boolean(true));
+    }
+
+    #[test]
+    fn assign_fields_needs_instances() {
+        let (_, success) = try_run_program(
+            "class Point {}
+             assign_fields(Point(), 1);",
+        );
+        assert!(!success);
+    }
+
+    #[test]
+    fn super_in_class_nested_in_method() {
+        let interpreter = run_program(
+            "class A { hi() { return \"A\"; } }
+             class B < A {
+                 hi() { return \"B\"; }
+                 run() {
+                     class C < A { hi() { return \"C>\" + super.hi(); } }
+                     class D < C { hi() { return \"D>\" + super.hi(); } }
+                     return D().hi() + \"|\" + super.hi();
+                 }
+             }
+             var result = B().run();",
+        );
+        assert_eq!(global(&interpreter, "result"), string("D>C>A|A"));
+    }
+
+    #[test]
+    fn group_by_parity() {
+        let interpreter = run_program(
+            "fun parity(n) {
+                 while (n >= 2) n = n - 2;
+                 if (n == 0) return \"even\";
+                 return \"odd\";
+             }",
+        );
+        let numbers = Object::array((1..=5).map(number).collect());
+        let groups = NativeGroupBy
+            .call(&interpreter, vec![numbers, global(&interpreter, "parity")], None)
+            .unwrap();
+
+        let Object::Map(groups) = groups else {
+            panic!("expected a map");
+        };
+        let groups = groups.borrow();
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups["odd"], Object::array(vec![number(1), number(3), number(5)]));
+        assert_eq!(groups["even"], Object::array(vec![number(2), number(4)]));
+    }
+
+    #[test]
+    fn group_by_needs_string_keys() {
+        let interpreter = run_program("fun same(n) { return n; }");
+        let numbers = Object::array(vec![number(1)]);
+        let result = NativeGroupBy.call(&interpreter, vec![numbers, global(&interpreter, "same")], None);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn step_hook_sees_every_statement() {
+        let steps = Rc::new(RefCell::new(Vec::new()));
+        let recorded = Rc::clone(&steps);
+
+        let (interpreter, success) = try_run_program_with(
+            "var a = 1;
+             while (a < 3) {
+                 a = a + 1;
+             }
+             print a;
+             print 1;",
+            |interpreter| {
+                interpreter.set_step_hook(Some(Box::new(move |line, kind| {
+                    recorded.borrow_mut().push((line, kind.to_string()));
+                })))
+            },
+        );
+        assert!(success);
+        assert_eq!(global(&interpreter, "a"), number(3));
+
+        let steps = steps.borrow().iter().map(|(l, k)| format!("{l}:{k}")).collect::<Vec<_>>();
+        assert_eq!(
+            steps,
+            ["1:var", "2:while", "2:block", "3:expression", "2:block", "3:expression", "5:print", "6:print"]
+        );
+    }
+
+    #[test]
+    fn bound_method_outlives_instance_variable() {
+        let interpreter = run_program(
+            "var greet;
+             {
+                 class Greeter {
+                     init(name) { this.name = name; }
+                     hi() { return \"hi \" + this.name; }
+                 }
+                 var greeter = Greeter(\"ada\");
+                 greet = greeter.hi;
+                 greeter = nil;
+             }
+             var result = greet();",
+        );
+        assert!(matches!(global(&interpreter, "greet"), Object::Func(_)));
+        assert_eq!(global(&interpreter, "result"), string("hi ada"));
+    }
+
+    #[test]
+    fn compose_applies_inner_first() {
+        let interpreter = run_program(
+            "fun inc(x) { return x + 1; }
+             fun dbl(x) { return x * 2; }
+             var inc_after_dbl = compose(inc, dbl);
+             var seven = inc_after_dbl(3);
+             var eight = compose(dbl, inc)(3);",
+        );
+        assert_eq!(global(&interpreter, "seven"), number(7));
+        assert_eq!(global(&interpreter, "eight"), number(8));
+    }
+
+    #[test]
+    fn compose_needs_callables() {
+        let (_, success) = try_run_program("fun inc(x) { return x + 1; } compose(inc, 1);");
+        assert!(!success);
+        let (_, success) = try_run_program("fun inc(x) { return x + 1; } compose(inc, inc)(1, 2);");
+        assert!(!success);
+    }
+
+    #[test]
+    fn panic_message_has_backtrace() {
+        let interpreter = run_program(
+            "fun inner() {
+                 panic(\"oh no\");
+             }
+             fun outer() {
+                 inner();
+             }
+             fun main() {
+                 outer();
+             }",
+        );
+
+        match interpreter.call_value(&global(&interpreter, "main"), vec![]) {
+            Err(LoxResult::RuntimeError { message, .. }) => assert_eq!(
+                message,
+                "panic() oh no\n  in panic() called on line 2\n  in inner() called on line 5\n  in outer() called on line 8\n  in main() called on line 0"
+            ),
+            _ => panic!("expected a runtime error"),
+        }
+    }
+
+    #[test]
+    fn backtrace_includes_callbacks_of_natives() {
+        let interpreter = run_program(
+            "fun boom(x) { panic(\"bad\"); }
+             fun run() { return group_by([1], boom); }",
+        );
+
+        match interpreter.call_value(&global(&interpreter, "run"), vec![]) {
+            Err(LoxResult::RuntimeError { message, .. }) => assert_eq!(
+                message,
+                "panic() bad\n  in panic() called on line 1\n  in boom() called on line 2\n  in group_by() called on line 2\n  in run() called on line 0"
+            ),
+            _ => panic!("expected a runtime error"),
+        }
+        assert!(interpreter.call_stack().is_empty());
+    }
+
+    #[test]
+    fn backtrace_includes_getters() {
+        let interpreter = run_program(
+            "class Bomb { boom { panic(\"bad\"); } }
+             fun run() { return Bomb().boom; }",
+        );
+
+        match interpreter.call_value(&global(&interpreter, "run"), vec![]) {
+            Err(LoxResult::RuntimeError { message, .. }) => assert_eq!(
+                message,
+                "panic() bad\n  in panic() called on line 1\n  in boom() called on line 2\n  in run() called on line 0"
+            ),
+            _ => panic!("expected a runtime error"),
+        }
+        assert!(interpreter.call_stack().is_empty());
+    }
+
+    #[test]
+    fn call_stack_unwinds_after_return() {
+        let interpreter = run_program(
+            "fun find(limit) {
+                 var i = 0;
+                 while (true) {
+                     if (i == limit) return i;
+                     i = i + 1;
+                 }
+             }
+             fun twice(limit) { return find(limit) + find(limit); }
+             var result = twice(3);",
+        );
+        assert_eq!(global(&interpreter, "result"), number(6));
+        assert!(interpreter.call_stack().is_empty());
+        assert_eq!(interpreter.backtrace(), "");
+    }
+
+    #[test]
+    fn call_stack_unwinds_after_error() {
+        let (interpreter, success) = try_run_program(
+            "fun fail() { return nil + 1; }
+             fun call_fail() { fail(); }
+             call_fail();",
+        );
+        assert!(!success);
+        assert!(interpreter.call_stack().is_empty());
+
+        // the interpreter is still usable and its stack starts out empty
+        let result = interpreter.call_value(&global(&interpreter, "call_fail"), vec![]);
+        assert!(result.is_err());
+        assert!(interpreter.call_stack().is_empty());
+    }
+
+    #[test]
+    fn for_loop_closures_capture_their_own_iteration() {
+        let interpreter = run_program(
+            "var first;
+             var second;
+             var third;
+             for (var i = 0; i < 3; i = i + 1) {
+                 fun get() { return i; }
+                 if (i == 0) first = get;
+                 if (i == 1) second = get;
+                 if (i == 2) third = get;
+             }
+             var a = first();
+             var b = second();
+             var c = third();",
+        );
+        assert_eq!(global(&interpreter, "a"), number(0));
+        assert_eq!(global(&interpreter, "b"), number(1));
+        assert_eq!(global(&interpreter, "c"), number(2));
+    }
+
+    #[test]
+    fn for_loop_body_changes_carry_over() {
+        let interpreter = run_program(
+            "var seen = \"\";
+             for (var i = 0; i < 6; i = i + 1) {
+                 seen = seen + num_to_str(i);
+                 i = i + 1;
+             }",
+        );
+        assert_eq!(global(&interpreter, "seen"), string("024"));
+    }
+
+    #[test]
+    fn chained_access_on_call_results() {
+        let interpreter = run_program(
+            "class Counter {
+                 init(start) { this.count = start; }
+                 next() { this.count = this.count + 1; return this; }
+             }
+             class Box {
+                 init(counter) { this.counter = counter; }
+             }
+             fun make() { return Box(Counter(1)); }
+             var count = make().counter.next().next().count;
+             var same = make().counter.next;
+             var result = same().count;",
+        );
+        assert_eq!(global(&interpreter, "count"), number(3));
+        assert_eq!(global(&interpreter, "result"), number(2));
+    }
+
+    #[test]
+    fn chained_access_on_non_instance_errors() {
+        let (_, success) = try_run_program(
+            "class Box { init() { this.value = 1; } }
+             fun make() { return Box(); }
+             make().value.missing();",
+        );
+        assert!(!success);
+    }
+
+    #[test]
+    fn ternary_picks_a_branch() {
+        let source = "
+            print true ? 1 : 2;
+            print nil ? 1 : 2;
+            var n = 5;
+            print n < 0 ? \"negative\" : n == 0 ? \"zero\" : \"positive\";
+            var calls = 0;
+            fun count() { calls = calls + 1; return calls; }
+            print false ? count() : 0;
+            print calls;
+        ";
+        assert_eq!(program_output(source, false), "1\n2\npositive\n0\n0\n");
+    }
+
+    #[test]
+    fn break_leaves_while_loop() {
+        let source = "var i = 0; while (true) { if (i == 3) break; print i; i = i + 1; } print \"done\";";
+        assert_eq!(program_output(source, false), "0\n1\n2\ndone\n");
+    }
+
+    #[test]
+    fn continue_skips_rest_of_while_body() {
+        let source = "var i = 0; while (i < 5) { i = i + 1; if (i == 2 or i == 4) continue; print i; }";
+        assert_eq!(program_output(source, false), "1\n3\n5\n");
+    }
+
+    #[test]
+    fn continue_in_for_loop_runs_increment() {
+        let source = "for (var i = 0; i < 6; i = i + 1) { if (i == 1) { i = 3; continue; } print i; }";
+        assert_eq!(program_output(source, false), "0\n4\n5\n");
+    }
+
+    #[test]
+    fn printing_an_instance_shows_its_class() {
+        let source = "class Point { init(x, y) { this.x = x; this.y = y; } } print Point(1, 2);";
+        assert_eq!(program_output(source, false), "<Point instance>\n");
+    }
+
+    #[test]
+    fn type_predicates_accept_only_their_type() {
+        let interpreter = run_program("class Point {} fun f() {} var p = Point();");
+        let values = [
+            number(1),
+            string("a"),
+            boolean(true),
+            nil(),
+            global(&interpreter, "f"),
+            global(&interpreter, "Point"),
+            global(&interpreter, "p"),
+            Object::array(vec![]),
+            Object::map(HashMap::new()),
+        ];
+        let predicates = [
+            "is_number",
+            "is_string",
+            "is_bool",
+            "is_nil",
+            "is_function",
+            "is_class",
+            "is_instance",
+            "is_array",
+            "is_map",
+        ];
+
+        for (i, predicate) in predicates.iter().enumerate() {
+            let Object::Native(native) = global(&interpreter, predicate) else {
+                panic!("{predicate} is not a native function");
+            };
+            for (j, value) in values.iter().enumerate() {
+                let result = native.func.call(&interpreter, vec![value.clone()], None).unwrap();
+                assert_eq!(result, boolean(i == j), "{predicate}({value})");
+            }
+        }
+
+        let Object::Native(is_function) = global(&interpreter, "is_function") else {
+            panic!("is_function is not a native function");
+        };
+        let clock = global(&interpreter, "clock");
+        assert_eq!(is_function.func.call(&interpreter, vec![clock], None).unwrap(), boolean(true));
+    }
+
+    #[test]
+    fn method_is_callable_on_instance() {
+        let source = "class Greeter { greet(name) { return \"hello \" + name; } } print Greeter().greet(\"bob\");";
+        assert_eq!(program_output(source, false), "hello bob\n");
+    }
+
+    #[test]
+    fn method_fetched_as_value_sees_later_field_changes() {
+        let source = "
+            class Counter {
+                init() { this.count = 0; }
+                show() { print this.count; }
+            }
+            var counter = Counter();
+            var show = counter.show;
+            counter.count = 3;
+            show();
+        ";
+        assert_eq!(program_output(source, false), "3\n");
+    }
+
+    #[test]
+    fn subclass_calls_inherited_method() {
+        let source = "
+            class Animal { speak() { return this.name + \" makes a sound\"; } }
+            class Dog < Animal { init(name) { this.name = name; } }
+            print Dog(\"rex\").speak();
+        ";
+        assert_eq!(program_output(source, false), "rex makes a sound\n");
+    }
+
+    #[test]
+    fn arithmetic_with_nil_operand() {
+        for operator in [plus(), minus(), star(), slash(), percent()] {
+            for (left, right) in [(nil(), number(1)), (number(1), nil())] {
+                let expr = Expr::Binary(Rc::new(BinaryExpr {
+                    left: Rc::new(Expr::Literal(Rc::new(LiteralExpr { value: Some(left) }))),
+                    operator: operator.clone(),
+                    right: Rc::new(Expr::Literal(Rc::new(LiteralExpr { value: Some(right) }))),
+                }));
+                match run(expr) {
+                    Err(LoxResult::RuntimeError { message, .. }) => assert_eq!(message, "Operand is nil."),
+                    other => panic!("expected a runtime error for '{}', got {:?}", operator.lexeme, other),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn super_method_is_bound_to_this() {
+        let source = "
+            class Doughnut { cook() { print \"fry \" + this.name; } }
+            class BostonCream < Doughnut {
+                init() { this.name = \"boston cream\"; }
+                cook() { super.cook(); print \"fill \" + this.name; }
+            }
+            BostonCream().cook();
+        ";
+        assert_eq!(program_output(source, false), "fry boston cream\nfill boston cream\n");
+    }
+
+    #[test]
+    fn static_method_called_through_class() {
+        let source = "
+            class Math {
+                class square(n) { return n * n; }
+            }
+            class MoreMath < Math {}
+            print Math.square(3);
+            print MoreMath.square(4);
+        ";
+        assert_eq!(program_output(source, false), "9\n16\n");
+    }
+
+    #[test]
+    fn static_method_is_not_an_instance_method() {
+        let (_, success) = try_run_program("class Math { class square(n) { return n * n; } } Math().square(3);");
+        assert!(!success);
+        let (_, success) = try_run_program("class Math {} Math.square(3);");
+        assert!(!success);
+    }
+
+    #[test]
+    fn getter_computes_property() {
+        let source = "
+            class Rect {
+                init(w, h) { this.w = w; this.h = h; }
+                area { return this.w * this.h; }
+            }
+            var rect = Rect(3, 4);
+            print rect.area;
+            rect.w = 5;
+            print rect.area;
+        ";
+        assert_eq!(program_output(source, false), "12\n20\n");
+    }
+
+    #[test]
+    fn calling_a_getter_calls_its_value() {
+        let (_, success) = try_run_program("class Rect { area { return 12; } } Rect().area();");
+        assert!(!success);
+    }
+
+    #[test]
+    fn array_literal_and_indexing() {
+        let interpreter = run_program(
+            "var a = [1, \"two\", [3]];
+             var first = a[0];
+             var nested = a[2][0];
+             var empty = [];
+             var size = len(a);",
+        );
+        assert_eq!(
+            global(&interpreter, "a"),
+            Object::array(vec![number(1), string("two"), Object::array(vec![number(3)])])
+        );
+        assert_eq!(global(&interpreter, "first"), number(1));
+        assert_eq!(global(&interpreter, "nested"), number(3));
+        assert_eq!(global(&interpreter, "empty"), Object::array(vec![]));
+        assert_eq!(global(&interpreter, "size"), number(3));
+    }
+
+    #[test]
+    fn array_index_assignment() {
+        let interpreter = run_program(
+            "var a = [1, 2, 3];
+             var b = a;
+             a[1] = a[0] + 10;
+             push(a, 4);
+             var last = pop(a);",
+        );
+        assert_eq!(global(&interpreter, "b"), Object::array(vec![number(1), number(11), number(3)]));
+        assert_eq!(global(&interpreter, "last"), number(4));
+    }
+
+    #[test]
+    fn array_index_errors() {
+        for source in ["[1, 2][2];", "[1, 2][-1];", "[1, 2][0.5];", "[1, 2][\"0\"];", "var a = [1]; a[1] = 2;", "1[0];"] {
+            let (_, success) = try_run_program(source);
+            assert!(!success, "{source}");
+        }
+    }
+
+    #[test]
+    fn arrays_and_maps_that_contain_themselves() {
+        let source = "
+            var a = [1];
+            push(a, a);
+            var b = [1];
+            push(b, b);
+            var m = {\"k\": 1};
+            m[\"k\"] = m;
+            print a == a;
+            print a == b;
+            print a == [1, a];
+            print m == m;
+            print a;
+            print m;
+            print sort_total([b, a]) == [a, b];
+        ";
+        assert_eq!(
+            program_output(source, false),
+            "true\ntrue\ntrue\ntrue\n[1, [...]]\n{\"k\": {...}}\ntrue\n"
+        );
+    }
+
+    // Counts up to `limit` and returns nil after that, like reading lines until the input ends.
+    struct CountTo {
+        limit: f64,
+        count: RefCell<f64>,
+    }
+
+    impl LoxCallable for CountTo {
+        fn call(&self, _interpreter: &Interpreter, _arguments: Vec<Object>, _klass: Option<Rc<Class>>) -> Result<Object, LoxResult> {
+            let mut count = self.count.borrow_mut();
+            if *count >= self.limit {
+                return Ok(Object::Nil);
+            }
+            *count += 1.0;
+            Ok(Object::Num(*count))
+        }
+
+        fn arity(&self) -> usize {
+            0
+        }
+    }
+
+    impl std::fmt::Display for CountTo {
+        fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+            write!(f, "<native fn>")
+        }
+    }
+
+    fn run_with_counter(source: &str) -> Interpreter {
+        let (interpreter, success) = try_run_program_with(source, |interpreter| {
+            let counter = CountTo { limit: 3.0, count: RefCell::new(0.0) };
+            let next = Native::new("next", Rc::new(counter));
+            interpreter.globals.borrow_mut().define("next", Object::Native(Rc::new(next)));
+        });
+        assert!(success);
+        interpreter
+    }
+
+    #[test]
+    fn while_condition_binds_variable() {
+        let interpreter = run_with_counter(
+            "var seen = [];
+             var getters = [];
+             while (var n = next()) {
+                 push(seen, n);
+                 fun get() { return n; }
+                 push(getters, get);
+             }
+             var first = getters[0]();",
+        );
+        assert_eq!(global(&interpreter, "seen"), Object::array(vec![number(1), number(2), number(3)]));
+        // every iteration has its own `n`
+        assert_eq!(global(&interpreter, "first"), number(1));
+    }
+
+    #[test]
+    fn for_condition_binds_variable() {
+        let interpreter = run_with_counter(
+            "var sum = 0;
+             var steps = 0;
+             for (var i = 0; var n = next(); i = i + 1) {
+                 sum = sum + n;
+                 steps = i + 1;
+             }",
+        );
+        assert_eq!(global(&interpreter, "sum"), number(6));
+        assert_eq!(global(&interpreter, "steps"), number(3));
+    }
+
+    #[test]
+    fn map_literal_and_indexing() {
+        let interpreter = run_program(
+            "var key = \"b\";
+             var m = {\"a\": 1, key: [2], \"c\" + \"d\": {}};
+             var a = m[\"a\"];
+             m[\"a\"] = a + 10;
+             m[\"new\"] = true;
+             var missing = m[\"nope\"];
+             var names = keys(m);
+             var has_new = has(m, \"new\");
+             var has_nope = has(m, \"nope\");",
+        );
+        assert_eq!(global(&interpreter, "a"), number(1));
+        assert_eq!(global(&interpreter, "missing"), nil());
+        assert_eq!(
+            global(&interpreter, "names"),
+            Object::array(vec![string("a"), string("b"), string("cd"), string("new")])
+        );
+        assert_eq!(global(&interpreter, "has_new"), boolean(true));
+        assert_eq!(global(&interpreter, "has_nope"), boolean(false));
+        assert_eq!(
+            global(&interpreter, "m").to_string(),
+            "{\"a\": 11, \"b\": [2], \"cd\": {}, \"new\": true}"
+        );
+    }
+
+    #[test]
+    fn map_keys_must_be_strings() {
+        for source in ["var m = {1: 2};", "var m = {}; m[1] = 2;", "var m = {}; print m[nil];"] {
+            let (_, success) = try_run_program(source);
+            assert!(!success, "{source}");
+        }
+    }
+
+    #[test]
+    fn unary_minus_needs_a_number() {
+        for operand in [boolean(true), string("abc")] {
+            let expr = Expr::Unary(Rc::new(UnaryExpr {
+                operator: minus(),
+                right: Rc::new(Expr::Literal(Rc::new(LiteralExpr { value: Some(operand) }))),
+            }));
+            assert!(run(expr).is_err());
+        }
+
+        let expr = Expr::Unary(Rc::new(UnaryExpr {
+            operator: minus(),
+            right: Rc::new(Expr::Literal(Rc::new(LiteralExpr { value: Some(number(5)) }))),
+        }));
+        assert_eq!(run(expr).unwrap(), number(-5));
+    }
+
+    #[test]
+    fn comparison_needs_numbers() {
+        for source in ["\"a\" < \"b\";", "true >= false;", "nil <= nil;", "1 > \"0\";"] {
+            let (_, success) = try_run_program(source);
+            assert!(!success, "{source}");
+        }
+
+        let interpreter = run_program("var less = 1 < 2; var same = \"a\" == \"a\"; var differ = 1 != \"1\";");
+        assert_eq!(global(&interpreter, "less"), boolean(true));
+        assert_eq!(global(&interpreter, "same"), boolean(true));
+        assert_eq!(global(&interpreter, "differ"), boolean(true));
+    }
+
+    #[test]
+    fn plus_concatenates_strings_with_other_values() {
+        let interpreter = run_program("var a = \"x\" + 5; var b = 5 + \"x\"; var c = \"ok: \" + true;");
+        assert_eq!(global(&interpreter, "a"), string("x5"));
+        assert_eq!(global(&interpreter, "b"), string("5x"));
+        assert_eq!(global(&interpreter, "c"), string("ok: true"));
+
+        for source in ["nil + \"x\";", "fun f() {} f + \"x\";", "class A {} \"x\" + A;"] {
+            let (_, success) = try_run_program(source);
+            assert!(!success, "{source}");
+        }
+    }
+
+    #[test]
+    fn bind_swaps_this() {
+        let source = "
+            var outer = \"outer\";
+            class Pet {
+                init(name) { this.name = name; }
+                greet() { return outer + \" \" + this.name; }
+            }
+            var rex = Pet(\"rex\");
+            var tom = Pet(\"tom\");
+            var greet = bind(rex.greet, tom);
+            print greet();
+            print bind(greet, rex)();
+            print rex.greet();
+        ";
+        assert_eq!(program_output(source, false), "outer tom\nouter rex\nouter rex\n");
+    }
+
+    #[test]
+    fn bind_needs_method_and_instance() {
+        for source in ["fun f() {} bind(f, nil);", "class A { m() {} } bind(A().m, 1);", "class A {} bind(clock, A());"] {
+            let (_, success) = try_run_program(source);
+            assert!(!success, "{source}");
+        }
+    }
+
+    #[test]
+    fn resolved_read_two_blocks_out() {
+        let source = "
+            {
+                var a = \"outer\";
+                {
+                    var b = \"middle\";
+                    {
+                        var a = \"shadow\";
+                        fun show() { print a + \" \" + b; }
+                        show();
+                    }
+                    print a;
+                }
+            }
+        ";
+        assert_eq!(program_output(source, false), "shadow middle\nouter\n");
+    }
+
+    #[test]
+    fn closure_assigns_to_captured_variable() {
+        let source = "
+            var count;
+            {
+                var n = 0;
+                fun increment() { n = n + 1; }
+                increment();
+                increment();
+                count = n;
+            }
+        ";
+        let interpreter = run_program(source);
+        assert_eq!(global(&interpreter, "count"), number(2));
+    }
+
+    #[test]
+    fn repl_echoes_a_lone_expression() {
+        assert_eq!(repl_output("1 + 2;"), "3\n");
+        assert_eq!(repl_output("\"a\" + \"b\";"), "ab\n");
+    }
+
+    #[test]
+    fn step_hook_sees_echoed_repl_expressions() {
+        let steps = Rc::new(RefCell::new(Vec::new()));
+        let recorded = Rc::clone(&steps);
+        let interpreter = Interpreter::new().with_output(Box::new(SharedBuffer::default()));
+        interpreter.set_step_hook(Some(Box::new(move |line, kind| {
+            recorded.borrow_mut().push((line, kind.to_string()));
+        })));
+
+        let tokens = Scanner::new("1 + 2;").tokenize().unwrap();
+        let statements = Rc::new(Parser::new(tokens).parse().unwrap());
+        assert!(interpreter.interpret_repl(statements));
+        assert_eq!(*steps.borrow(), [(1, "expression".to_string())]);
+    }
+
+    #[test]
+    fn repl_does_not_echo_statements() {
+        assert_eq!(repl_output("print 1 + 2;"), "3\n");
+        assert_eq!(repl_output("var a = 1;"), "");
+        assert_eq!(repl_output("fun f() {}"), "");
+        assert_eq!(repl_output("1; 2;"), "");
+    }
+
+    #[test]
+    fn fields_are_listed_in_the_order_they_were_set() {
+        let source = "
+            class Point {}
+            var p = Point();
+            p.y = 1;
+            p.x = 2;
+            p.z = 3;
+            p.y = 4;
+            print fields(p);
+            print p.y;
+        ";
+        assert_eq!(program_output(source, false), "[\"y\", \"x\", \"z\"]\n4\n");
+    }
+
+    #[test]
+    fn substring_out_of_order_or_out_of_range_is_a_runtime_error() {
+        assert_eq!(program_output("print substring(\"hello\", 1, 5);", false), "ello\n");
+        for source in [
+            "substring(\"hello\", 3, 1);",
+            "substring(\"hello\", 3, 6);",
+            "substring(\"hello\", 10, 12);",
+        ] {
+            let (_, success) = try_run_program(source);
+            assert!(!success, "{source}");
+        }
+    }
+
+    #[test]
+    fn partial_of_a_native_with_optional_arguments() {
+        let (_, success) = try_run_program("var check = partial(assert, [true]); check(); check(\"message\");");
+        assert!(success);
+        let (_, success) = try_run_program("partial(assert, [false])();");
+        assert!(!success);
+    }
+
+    #[test]
+    fn instances_are_equal_only_to_themselves() {
+        let source = "
+            class Point {}
+            var a = Point();
+            a.x = 1;
+            a.y = 2;
+            var b = Point();
+            b.y = 2;
+            b.x = 1;
+            var c = a;
+            print a == b;
+            print a == c;
+        ";
+        assert_eq!(program_output(source, false), "false\ntrue\n");
+    }
+
+    #[test]
+    fn class_reflection_includes_inherited_methods() {
+        let source = "
+            class Animal {
+                speak() {}
+                eat() {}
+            }
+            class Dog < Animal {
+                speak() {}
+                fetch() {}
+            }
+            print class_name(Dog);
+            print class_name(Dog());
+            print method_names(Dog);
+            print method_names(Dog()) == method_names(Dog);
+        ";
+        assert_eq!(
+            program_output(source, false),
+            "Dog\nDog\n[\"eat\", \"fetch\", \"speak\"]\ntrue\n"
+        );
+        assert!(!try_run_program("class_name(1);").1);
+    }
+
+    #[test]
+    fn math_natives() {
+        let source = "
+            print sqrt(16);
+            print pow(2, 10);
+            print pow(4, 0.5);
+            print floor(2.7);
+            print floor(-2.2);
+            print ceil(2.2);
+            print ceil(-2.7);
+            print abs(-3.5);
+            print abs(3);
+        ";
+        assert_eq!(program_output(source, false), "4\n1024\n2\n2\n-3\n3\n-2\n3.5\n3\n");
+    }
+
+    #[test]
+    fn math_natives_reject_bad_arguments() {
+        for source in ["sqrt(-1);", "sqrt(\"4\");", "pow(2, nil);", "floor(true);", "ceil(nil);", "abs(\"x\");"] {
+            assert!(!try_run_program(source).1, "{} should fail", source);
+        }
+    }
+
+    #[test]
+    fn type_names_every_kind_of_value() {
+        let source = "
+            class Point {
+                norm() { return 0; }
+            }
+            fun f() {}
+            var p = Point();
+            print type(1.5);
+            print type(\"s\");
+            print type(false);
+            print type(nil);
+            print type(f);
+            print type(clock);
+            print type(p.norm);
+            print type(Point);
+            print type(p);
+            print type([1]);
+            print type({\"a\": 1});
+            print type(type(1));
+        ";
+        assert_eq!(
+            program_output(source, false),
+            "number\nstring\nboolean\nnil\nfunction\nfunction\nfunction\nclass\ninstance\narray\nmap\nstring\n"
+        );
+    }
+
+    #[test]
+    fn zip_with_combines_pairs() {
+        let source = "
+            fun add(a, b) { return a + b; }
+            print zip_with([1, 2, 3], [10, 20, 30], add);
+            print zip_with([1, 2, 3], [10], add);
+            print zip_with([], [1], add);
+        ";
+        assert_eq!(program_output(source, false), "[11, 22, 33]\n[11]\n[]\n");
+        assert!(!try_run_program("zip_with([1], [2], 3);").1);
+        assert!(!try_run_program("zip_with([1], nil, clock);").1);
+    }
+
+    #[test]
+    fn string_builder_matches_concatenation() {
+        let source = "
+            var sb = string_builder();
+            var s = \"\";
+            for (var i = 0; i < 500; i = i + 1) {
+                sb_append(sb, i);
+                sb_append(sb, \",\");
+                s = s + i + \",\";
+            }
+            var built = sb_build(sb);
+            print built == s;
+            print len(built);
+            print sb_build(sb_append(sb_append(string_builder(), \"a\"), true));
+        ";
+        assert_eq!(program_output(source, false), "true\n1890\natrue\n");
+        assert!(!try_run_program("sb_append(\"a\", \"b\");").1);
+        assert!(!try_run_program("sb_build(nil);").1);
+    }
+
+    #[test]
+    fn assert_passes_on_truthy_values() {
+        let source = "
+            assert(true);
+            assert(0, \"zero is truthy\");
+            assert(\"\");
+            assert(1 < 2, \"math works\");
+            print \"done\";
+        ";
+        assert_eq!(program_output(source, false), "done\n");
+    }
+
+    #[test]
+    fn assert_fails_on_falsy_values() {
+        assert!(!try_run_program("assert(false);").1);
+        assert!(!try_run_program("assert(nil, \"was nil\");").1);
+        // only the statements before the failed assertion ran
+        let (interpreter, success) = try_run_program("var a = 1; assert(a == 2); a = 3;");
+        assert!(!success);
+        assert_eq!(global(&interpreter, "a"), number(1));
+    }
+
+    #[test]
+    fn assert_reports_its_message() {
+        let interpreter = Interpreter::new();
+        let message = |arguments: Vec<Object>| match NativeAssert.call(&interpreter, arguments, None) {
+            Err(LoxResult::NativeError { message }) => message,
+            other => panic!("expected a native error, got {:?}", other),
+        };
+
+        assert_eq!(message(vec![boolean(false), string("custom")]), "custom");
+        assert_eq!(message(vec![nil()]), "Assertion failed.");
+    }
+
+    #[test]
+    fn optional_arguments_widen_the_arity_check() {
+        assert!(!try_run_program("assert();").1);
+        assert!(!try_run_program("assert(true, \"a\", \"b\");").1);
+        assert_eq!(arity_error(&NativeAssert, 3).as_deref(), Some("Expected 1 to 2 arguments but got 3."));
+        assert_eq!(arity_error(&NativeClock, 1).as_deref(), Some("Expected 0 arguments but got 1."));
+    }
 }
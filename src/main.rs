@@ -17,73 +17,136 @@ mod function;
 mod resolver;
 mod class;
 mod instance;
+mod config;
+mod random;
+mod purity;
+#[cfg(test)]
+mod test_utils;
 
-use error::*;
+use config::Config;
 use interpreter::*;
 use parser::*;
 use scanner::*;
 use resolver::*;
+use stmt::Stmt;
 
 use std::env;
 use std::fs;
 use std::io;
-use std::io::Write;
-use std::cmp::Ordering;
+use std::io::{BufRead, Write};
 use std::rc::Rc;
 
+enum Mode {
+    Repl,
+    Files(Vec<String>),
+}
+
+fn parse_args(args: &[String]) -> Mode {
+    match args.get(1..) {
+        Some(paths) if !paths.is_empty() => Mode::Files(paths.to_vec()),
+        _ => Mode::Repl,
+    }
+}
+
 struct Lox {
     interpreter: Interpreter,
 }
 
 impl Lox {
     fn new() -> Self {
+        Lox::with_config(Config::default())
+    }
+
+    fn with_config(config: Config) -> Self {
         Lox {
-            interpreter: Interpreter::new(),
+            interpreter: Interpreter::with_config(config),
         }
     }
-    fn run_file(&mut self, path: &str) -> io::Result<()> {
-        let content = fs::read_to_string(path)?;
 
-        if self.run(&content).is_err() {
+    fn run_files(&mut self, paths: &[String]) -> io::Result<()> {
+        let sources = paths
+            .iter()
+            .map(fs::read_to_string)
+            .collect::<io::Result<Vec<String>>>()?;
+
+        if !self.run_sources(&sources) {
             std::process::exit(1);
         }
 
         Ok(())
     }
 
+    // All sources share one interpreter, so later sources see what earlier ones defined. Stops at
+    // the first source that fails.
+    fn run_sources(&mut self, sources: &[String]) -> bool {
+        sources.iter().all(|source| self.run(source))
+    }
+
     fn run_repl(&mut self) -> io::Result<()> {
+        self.repl(io::stdin().lock())
+    }
+
+    // Every line is run on its own. A line that fails has already reported its error, and since
+    // blocks restore the environment when they bail out, the next line starts from the globals
+    // as the failed line left them.
+    fn repl(&mut self, mut input: impl BufRead) -> io::Result<()> {
         let mut line = String::new();
         loop {
             print!("> ");
-            io::stdout().flush().unwrap();
-            io::stdin().read_line(&mut line).unwrap();
-            if matches!(line.as_str(), "exit" | "exit()" | "quit" | "quit()") {
-                std::process::exit(0);
-            }
-            let _ = self.run(&line);
+            io::stdout().flush()?;
             line.clear();
+            // end of input
+            if input.read_line(&mut line)? == 0 {
+                println!();
+                return Ok(());
+            }
+            if matches!(line.trim(), "exit" | "exit()" | "quit" | "quit()") {
+                return Ok(());
+            }
+            self.run_repl_line(&line);
+        }
+    }
+
+    // Returns whether the source ran without any error. Errors are reported where they occur.
+    fn run(&mut self, source_code: &str) -> bool {
+        match self.prepare(source_code, false) {
+            Some(statements) => self.interpreter.interpret(statements),
+            None => false,
+        }
+    }
+
+    // Same as `run`, but a line holding a single expression statement prints its value.
+    fn run_repl_line(&mut self, source_code: &str) -> bool {
+        match self.prepare(source_code, true) {
+            Some(statements) => self.interpreter.interpret_repl(statements),
+            None => false,
         }
     }
 
-    fn run(&mut self, source_code: &str) -> Result<(), LoxResult> {
+    // Scans, parses and resolves the source. Returns `None` if any of those reported an error. In
+    // the REPL bare expressions are how values get looked at, so they don't warn there.
+    fn prepare(&self, source_code: &str, repl: bool) -> Option<Rc<Vec<Rc<Stmt>>>> {
         let mut scanner = Scanner::new(source_code);
-        let tokens = scanner.tokenize()?;
+        let tokens = scanner.tokenize().ok()?;
 
         let mut parser = Parser::new(tokens);
-        let statements = parser.parse()?;
-
+        let statements = parser.parse().ok()?;
 
-        if parser.success() {
-            let resolver = Resolver::new(&self.interpreter);
-            let s = Rc::new(statements);
+        if !parser.success() {
+            return None;
+        }
 
-            resolver.resolve(Rc::clone(&s))?;
+        let resolver = Resolver::new(&self.interpreter)
+            .warn_uninitialized(self.interpreter.config().warn_uninitialized)
+            .forbid_init_calls(self.interpreter.config().forbid_init_calls)
+            .warn_unused_results(self.interpreter.config().warn_unused_results && !repl);
+        let s = Rc::new(statements);
 
-            if resolver.success() {
-                self.interpreter.interpret(Rc::clone(&s));
-            }
+        if resolver.resolve(Rc::clone(&s)).is_err() || !resolver.success() {
+            return None;
         }
-        Ok(())
+
+        Some(s)
     }
 }
 
@@ -92,17 +155,138 @@ fn main() -> std::io::Result<()> {
 
     let mut lox = Lox::new();
 
-    match args.len().cmp(&2) {
-        Ordering::Greater => {
-            println!("USAGE: ./program [source_file]");
-            std::process::exit(1);
-        }
-        Ordering::Less => {
-            lox.run_repl()?;
+    match parse_args(&args) {
+        Mode::Repl => lox.run_repl()?,
+        Mode::Files(paths) => lox.run_files(&paths)?,
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::object::Object;
+    use crate::token::{Token, TokenType};
+    use crate::test_utils::SharedBuffer;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|a| a.to_string()).collect()
+    }
+
+    fn global(lox: &Lox, name: &str) -> Option<Object> {
+        let token = Token::new(TokenType::Identifier, name.to_string(), None, 1, 0);
+        let value = lox.interpreter.globals.borrow().get(&token).ok();
+        value
+    }
+
+    #[test]
+    fn parse_args_without_paths_is_repl() {
+        assert!(matches!(parse_args(&args(&["rawwr"])), Mode::Repl));
+    }
+
+    #[test]
+    fn parse_args_collects_all_paths() {
+        match parse_args(&args(&["rawwr", "prelude.lox", "main.lox"])) {
+            Mode::Files(paths) => assert_eq!(paths, args(&["prelude.lox", "main.lox"])),
+            Mode::Repl => panic!("expected files"),
         }
-        Ordering::Equal => {
-            lox.run_file(&args[1])?;
+    }
+
+    #[test]
+    fn sources_share_globals() {
+        let mut lox = Lox::new();
+        let sources = args(&["var greeting = \"hi\";", "var copy = greeting;"]);
+
+        assert!(lox.run_sources(&sources));
+        assert_eq!(global(&lox, "copy"), Some(Object::Str("hi".to_string())));
+    }
+
+    #[test]
+    fn sources_stop_at_first_failure() {
+        let mut lox = Lox::new();
+        let sources = args(&["var a = 1;", "var b = undefined;", "var c = 3;"]);
+
+        assert!(!lox.run_sources(&sources));
+        assert_eq!(global(&lox, "a"), Some(Object::Num(1.0)));
+        assert_eq!(global(&lox, "c"), None);
+    }
+
+    #[test]
+    fn config_reaches_interpreter() {
+        let config = Config {
+            buffered: true,
+            warn_uninitialized: true,
+            forbid_init_calls: true,
+            warn_unused_results: true,
+            seed: Some(7),
+        };
+        let mut lox = Lox::with_config(config.clone());
+        assert_eq!(lox.interpreter.config(), &config);
+
+        // warnings don't make a run fail
+        assert!(lox.run("{ var a; print a; }"));
+        assert!(!lox.run("class Foo {} Foo().init();"));
+    }
+
+    #[test]
+    fn repl_lines_share_globals() {
+        let mut lox = Lox::new();
+
+        assert!(lox.run_repl_line("var a = 1;"));
+        assert!(lox.run_repl_line("a = a + 1;"));
+        assert!(!lox.run_repl_line("b;"));
+        assert_eq!(global(&lox, "a"), Some(Object::Num(2.0)));
+    }
+
+    #[test]
+    fn repl_keeps_going_after_errors() {
+        let mut lox = Lox::new();
+        let input = "var a = 1;\n{ var b = 2; a = nil + 1; }\nvar = ;\na = a + 1;\nexit\na = 10;\n";
+
+        assert!(lox.repl(input.as_bytes()).is_ok());
+        assert_eq!(global(&lox, "a"), Some(Object::Num(2.0)));
+        // the failed block didn't leave its scope behind
+        assert!(!lox.run_repl_line("b;"));
+        assert!(lox.run_repl_line("var c = a;"));
+        assert_eq!(global(&lox, "c"), Some(Object::Num(2.0)));
+    }
+
+    // Hands out one line per read and notes what was written to `output` by then.
+    struct LineByLine {
+        lines: Vec<&'static str>,
+        output: SharedBuffer,
+        seen: Vec<String>,
+    }
+
+    impl io::Read for LineByLine {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.seen.push(String::from_utf8(self.output.0.borrow().clone()).unwrap());
+            if self.lines.is_empty() {
+                return Ok(0);
+            }
+            let line = self.lines.remove(0);
+            buf[..line.len()].copy_from_slice(line.as_bytes());
+            Ok(line.len())
         }
     }
-    Ok(())
+
+    #[test]
+    fn buffered_repl_flushes_before_reading_input() {
+        let output = SharedBuffer::default();
+        let config = Config {
+            buffered: true,
+            ..Config::default()
+        };
+        let mut lox = Lox {
+            interpreter: Interpreter::with_config(config).with_output(Box::new(output.clone())),
+        };
+        let mut input = io::BufReader::new(LineByLine {
+            lines: vec!["print 1;\n", "print 2; print nil + 1;\n"],
+            output,
+            seen: Vec::new(),
+        });
+
+        assert!(lox.repl(&mut input).is_ok());
+        assert_eq!(input.get_ref().seen, ["", "1\n", "1\n2\n"]);
+    }
 }
@@ -1,27 +1,48 @@
+use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::collections::HashMap;
 use std::fmt;
 use std::ptr;
 use std::rc::Rc;
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use crate::callable::*;
 use crate::object::Object;
 use crate::interpreter::Interpreter;
 use crate::error::*;
 use crate::class::*;
+use crate::random::Rng;
+use crate::utils::is_digit;
 
+// The longest string, in bytes, a native will build. Anything that would be longer is an error,
+// so a script can't make the interpreter abort on a failed allocation.
+const MAX_STRING_LENGTH: usize = 1 << 28;
+
+// A native together with the name it is registered under, which is what shows up when it's
+// printed.
 #[derive(Clone)]
 pub struct Native {
+    pub name: String,
     pub func: Rc<dyn LoxCallable>,
 }
 
+impl Native {
+    pub fn new(name: &str, func: Rc<dyn LoxCallable>) -> Self {
+        Native {
+            name: name.to_string(),
+            func,
+        }
+    }
+}
+
 impl fmt::Display for Native {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "<Native function>")
+        write!(f, "<native fn {}>", self.name)
     }
 }
 
 impl fmt::Debug for Native {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "<Native function>")
+        write!(f, "{}", self)
     }
 }
 
@@ -34,6 +55,22 @@ impl PartialEq for Native {
     } 
 }
 
+// Every native prints the same, `Native` adds the name it's registered under.
+macro_rules! native_display {
+    ($native:ty) => {
+        impl fmt::Display for $native {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "<native fn>")
+            }
+        }
+    };
+}
+
+// The elements of `array` as they are now. Natives that call back into Lox loop over this rather
+// than the array itself, so the callback is free to modify the array while they do.
+fn snapshot(array: &RefCell<Vec<Object>>) -> Vec<Object> {
+    array.borrow().clone()
+}
 
 pub struct NativeClock;
 
@@ -51,11 +88,7 @@ impl LoxCallable for NativeClock {
     }
 }
 
-impl fmt::Display for NativeClock {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "<native function>")
-    }
-}
+native_display!(NativeClock);
 
 
 pub struct NativeNumToString;
@@ -70,8 +103,2647 @@ impl LoxCallable for NativeNumToString {
     }
 }
 
-impl fmt::Display for NativeNumToString {
+native_display!(NativeNumToString);
+
+// Whether `s` is a number the way the scanner reads one, optionally negated: digits with an
+// optional fraction. So no whitespace, exponents, `inf` or `NaN`.
+fn is_number_literal(s: &str) -> bool {
+    let unsigned = s.strip_prefix('-').unwrap_or(s);
+    let (whole, fraction) = match unsigned.split_once('.') {
+        Some((whole, fraction)) => (whole, Some(fraction)),
+        None => (unsigned, None),
+    };
+    let all_digits = |part: &str| !part.is_empty() && part.chars().all(is_digit);
+    all_digits(whole) && fraction.is_none_or(all_digits)
+}
+
+pub struct NativeStrToNum;
+
+impl LoxCallable for NativeStrToNum {
+    // The inverse of `num_to_str`. Gives nil for strings that aren't a number, so callers can
+    // check for that instead of failing.
+    fn call(&self, _interpreter: &Interpreter, arguments: Vec<Object>, klass: Option<Rc<Class>>) -> Result<Object, LoxResult> {
+        match &arguments[0] {
+            Object::Str(s) if is_number_literal(s) => match s.parse::<f64>() {
+                Ok(n) if n.is_finite() => Ok(Object::Num(n)),
+                _ => Ok(Object::Nil),
+            },
+            Object::Str(_) => Ok(Object::Nil),
+            _ => Err(LoxResult::native_error("str_to_num() argument must be a string.")),
+        }
+    }
+
+    fn arity(&self) -> usize {
+        1
+    }
+}
+
+native_display!(NativeStrToNum);
+
+pub struct NativeDivRound;
+
+impl LoxCallable for NativeDivRound {
+    fn call(&self, _interpreter: &Interpreter, arguments: Vec<Object>, klass: Option<Rc<Class>>) -> Result<Object, LoxResult> {
+        let (a, b) = match (&arguments[0], &arguments[1]) {
+            (Object::Num(a), Object::Num(b)) => (*a, *b),
+            _ => return Err(LoxResult::native_error("div_round() operands must be numbers.")),
+        };
+
+        if b == 0.0 {
+            return Err(LoxResult::native_error("div_round() division by zero."));
+        }
+
+        let quotient = a / b;
+        match &arguments[2] {
+            Object::Str(mode) => match mode.as_str() {
+                "floor" => Ok(Object::Num(quotient.floor())),
+                "ceil" => Ok(Object::Num(quotient.ceil())),
+                "trunc" => Ok(Object::Num(quotient.trunc())),
+                "round" => Ok(Object::Num(quotient.round())),
+                _ => Err(LoxResult::native_error(&format!(
+                    "div_round() unknown rounding mode '{mode}', expected \"floor\", \"ceil\", \"trunc\" or \"round\"."
+                ))),
+            },
+            _ => Err(LoxResult::native_error("div_round() rounding mode must be a string.")),
+        }
+    }
+
+    fn arity(&self) -> usize {
+        3
+    }
+}
+
+native_display!(NativeDivRound);
+
+pub struct NativeHash;
+
+impl LoxCallable for NativeHash {
+    fn call(&self, _interpreter: &Interpreter, arguments: Vec<Object>, klass: Option<Rc<Class>>) -> Result<Object, LoxResult> {
+        match arguments[0].stable_hash() {
+            // keep the 53 most significant bits so the hash survives the conversion to f64
+            Some(hash) => Ok(Object::Num((hash >> 11) as f64)),
+            None => Err(LoxResult::native_error(&format!(
+                "hash() can't hash '{}', only numbers, strings, booleans and nil are hashable.",
+                arguments[0]
+            ))),
+        }
+    }
+
+    fn arity(&self) -> usize {
+        1
+    }
+}
+
+native_display!(NativeHash);
+
+pub struct NativeIsNan;
+
+impl LoxCallable for NativeIsNan {
+    fn call(&self, _interpreter: &Interpreter, arguments: Vec<Object>, klass: Option<Rc<Class>>) -> Result<Object, LoxResult> {
+        match arguments[0] {
+            Object::Num(n) => Ok(Object::Bool(n.is_nan())),
+            _ => Err(LoxResult::native_error("is_nan() argument must be a number.")),
+        }
+    }
+
+    fn arity(&self) -> usize {
+        1
+    }
+}
+
+native_display!(NativeIsNan);
+
+pub struct NativeIsInf;
+
+impl LoxCallable for NativeIsInf {
+    fn call(&self, _interpreter: &Interpreter, arguments: Vec<Object>, klass: Option<Rc<Class>>) -> Result<Object, LoxResult> {
+        match arguments[0] {
+            Object::Num(n) => Ok(Object::Bool(n.is_infinite())),
+            _ => Err(LoxResult::native_error("is_inf() argument must be a number.")),
+        }
+    }
+
+    fn arity(&self) -> usize {
+        1
+    }
+}
+
+native_display!(NativeIsInf);
+
+pub struct NativeIsFinite;
+
+impl LoxCallable for NativeIsFinite {
+    fn call(&self, _interpreter: &Interpreter, arguments: Vec<Object>, klass: Option<Rc<Class>>) -> Result<Object, LoxResult> {
+        match arguments[0] {
+            Object::Num(n) => Ok(Object::Bool(n.is_finite())),
+            _ => Err(LoxResult::native_error("is_finite() argument must be a number.")),
+        }
+    }
+
+    fn arity(&self) -> usize {
+        1
+    }
+}
+
+native_display!(NativeIsFinite);
+
+pub struct NativeEnumerate;
+
+impl LoxCallable for NativeEnumerate {
+    fn call(&self, _interpreter: &Interpreter, arguments: Vec<Object>, klass: Option<Rc<Class>>) -> Result<Object, LoxResult> {
+        match &arguments[0] {
+            Object::Array(array) => Ok(Object::array(
+                array
+                    .borrow()
+                    .iter()
+                    .enumerate()
+                    .map(|(i, value)| Object::array(vec![Object::Num(i as f64), value.clone()]))
+                    .collect(),
+            )),
+            _ => Err(LoxResult::native_error("enumerate() argument must be an array.")),
+        }
+    }
+
+    fn arity(&self) -> usize {
+        1
+    }
+}
+
+native_display!(NativeEnumerate);
+
+pub struct NativePartial;
+
+impl LoxCallable for NativePartial {
+    fn call(&self, _interpreter: &Interpreter, arguments: Vec<Object>, klass: Option<Rc<Class>>) -> Result<Object, LoxResult> {
+        let Some((func, _)) = as_callable(&arguments[0]) else {
+            return Err(LoxResult::native_error("partial() first argument must be callable."));
+        };
+        let Object::Array(bound) = &arguments[1] else {
+            return Err(LoxResult::native_error("partial() second argument must be an array."));
+        };
+
+        let bound = bound.borrow().clone();
+        if bound.len() > func.arity() {
+            return Err(LoxResult::native_error(&format!(
+                "partial() got {} arguments for a function that takes {}.",
+                bound.len(),
+                func.arity()
+            )));
+        }
+
+        Ok(Object::Native(Rc::new(Native::new(
+            "partial",
+            Rc::new(PartialFunction {
+                callee: arguments[0].clone(),
+                arity: func.arity() - bound.len(),
+                min_arity: func.min_arity().saturating_sub(bound.len()),
+                bound,
+            }),
+        ))))
+    }
+
+    fn arity(&self) -> usize {
+        2
+    }
+}
+
+native_display!(NativePartial);
+
+// The callable returned by `partial()`: calls `callee` with `bound` in front of its own arguments.
+struct PartialFunction {
+    callee: Object,
+    bound: Vec<Object>,
+    arity: usize,
+    // The bound arguments can already cover the optional ones of `callee`, then none are needed.
+    min_arity: usize,
+}
+
+impl LoxCallable for PartialFunction {
+    fn call(&self, interpreter: &Interpreter, arguments: Vec<Object>, klass: Option<Rc<Class>>) -> Result<Object, LoxResult> {
+        let mut all = self.bound.clone();
+        all.extend(arguments);
+        interpreter.call_value(&self.callee, all)
+    }
+
+    fn arity(&self) -> usize {
+        self.arity
+    }
+
+    fn min_arity(&self) -> usize {
+        self.min_arity
+    }
+}
+
+impl fmt::Display for PartialFunction {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "<native function>")
+        write!(f, "<partial {}>", self.callee)
+    }
+}
+
+pub struct NativeSortTotal;
+
+impl LoxCallable for NativeSortTotal {
+    fn call(&self, _interpreter: &Interpreter, arguments: Vec<Object>, klass: Option<Rc<Class>>) -> Result<Object, LoxResult> {
+        match &arguments[0] {
+            Object::Array(array) => {
+                let mut sorted = array.borrow().clone();
+                sorted.sort_by(|a, b| a.total_cmp(b));
+                Ok(Object::array(sorted))
+            }
+            _ => Err(LoxResult::native_error("sort_total() argument must be an array.")),
+        }
+    }
+
+    fn arity(&self) -> usize {
+        1
+    }
+}
+
+native_display!(NativeSortTotal);
+
+pub struct NativeObjectId;
+
+impl LoxCallable for NativeObjectId {
+    // The id is the address of the shared allocation, so it stays the same for as long as the
+    // object is alive. Values like numbers and strings are copied around and have no identity.
+    fn call(&self, _interpreter: &Interpreter, arguments: Vec<Object>, klass: Option<Rc<Class>>) -> Result<Object, LoxResult> {
+        let address = match &arguments[0] {
+            Object::Instance(i) => Rc::as_ptr(i) as *const () as usize,
+            Object::Func(f) => Rc::as_ptr(f) as *const () as usize,
+            Object::Native(n) => Rc::as_ptr(n) as *const () as usize,
+            Object::Class(c) => Rc::as_ptr(c) as *const () as usize,
+            Object::Array(a) => Rc::as_ptr(a) as *const () as usize,
+            Object::Map(m) => Rc::as_ptr(m) as *const () as usize,
+            Object::StringBuilder(b) => Rc::as_ptr(b) as *const () as usize,
+            other => {
+                return Err(LoxResult::native_error(&format!(
+                    "object_id() '{}' is a value and has no identity.",
+                    other
+                )))
+            }
+        };
+        Ok(Object::Num(address as f64))
+    }
+
+    fn arity(&self) -> usize {
+        1
+    }
+}
+
+native_display!(NativeObjectId);
+
+pub struct NativeWithTimeout;
+
+impl LoxCallable for NativeWithTimeout {
+    fn call(&self, interpreter: &Interpreter, arguments: Vec<Object>, klass: Option<Rc<Class>>) -> Result<Object, LoxResult> {
+        match arguments[1] {
+            Object::Num(millis) if millis >= 0.0 => {
+                interpreter.call_with_timeout(&arguments[0], Duration::from_millis(millis as u64))
+            }
+            _ => Err(LoxResult::native_error("with_timeout() milliseconds must be a non-negative number.")),
+        }
+    }
+
+    fn arity(&self) -> usize {
+        2
+    }
+}
+
+native_display!(NativeWithTimeout);
+
+pub struct NativeAssignFields;
+
+impl LoxCallable for NativeAssignFields {
+    fn call(&self, _interpreter: &Interpreter, arguments: Vec<Object>, klass: Option<Rc<Class>>) -> Result<Object, LoxResult> {
+        match (&arguments[0], &arguments[1]) {
+            (Object::Instance(target), Object::Instance(source)) => {
+                target.copy_fields_from(source);
+                Ok(arguments[0].clone())
+            }
+            _ => Err(LoxResult::native_error("assign_fields() arguments must be instances.")),
+        }
+    }
+
+    fn arity(&self) -> usize {
+        2
+    }
+}
+
+native_display!(NativeAssignFields);
+
+pub struct NativeGroupBy;
+
+impl LoxCallable for NativeGroupBy {
+    fn call(&self, interpreter: &Interpreter, arguments: Vec<Object>, klass: Option<Rc<Class>>) -> Result<Object, LoxResult> {
+        let Object::Array(array) = &arguments[0] else {
+            return Err(LoxResult::native_error("group_by() first argument must be an array."));
+        };
+
+        let elements = snapshot(array);
+        let mut groups: HashMap<String, Vec<Object>> = HashMap::new();
+        for element in elements {
+            match interpreter.call_value(&arguments[1], vec![element.clone()])? {
+                Object::Str(key) => groups.entry(key).or_default().push(element),
+                other => {
+                    return Err(LoxResult::native_error(&format!(
+                        "group_by() key '{}' is not a string.",
+                        other
+                    )))
+                }
+            }
+        }
+
+        Ok(Object::map(
+            groups
+                .into_iter()
+                .map(|(key, group)| (key, Object::array(group)))
+                .collect(),
+        ))
+    }
+
+    fn arity(&self) -> usize {
+        2
+    }
+}
+
+native_display!(NativeGroupBy);
+
+pub struct NativeTrim;
+
+impl LoxCallable for NativeTrim {
+    fn call(&self, _interpreter: &Interpreter, arguments: Vec<Object>, klass: Option<Rc<Class>>) -> Result<Object, LoxResult> {
+        match &arguments[0] {
+            Object::Str(s) => Ok(Object::Str(s.trim().to_string())),
+            _ => Err(LoxResult::native_error("trim() argument must be a string.")),
+        }
+    }
+
+    fn arity(&self) -> usize {
+        1
+    }
+}
+
+native_display!(NativeTrim);
+
+pub struct NativeTrimStart;
+
+impl LoxCallable for NativeTrimStart {
+    fn call(&self, _interpreter: &Interpreter, arguments: Vec<Object>, klass: Option<Rc<Class>>) -> Result<Object, LoxResult> {
+        match &arguments[0] {
+            Object::Str(s) => Ok(Object::Str(s.trim_start().to_string())),
+            _ => Err(LoxResult::native_error("trim_start() argument must be a string.")),
+        }
+    }
+
+    fn arity(&self) -> usize {
+        1
+    }
+}
+
+native_display!(NativeTrimStart);
+
+pub struct NativeTrimEnd;
+
+impl LoxCallable for NativeTrimEnd {
+    fn call(&self, _interpreter: &Interpreter, arguments: Vec<Object>, klass: Option<Rc<Class>>) -> Result<Object, LoxResult> {
+        match &arguments[0] {
+            Object::Str(s) => Ok(Object::Str(s.trim_end().to_string())),
+            _ => Err(LoxResult::native_error("trim_end() argument must be a string.")),
+        }
+    }
+
+    fn arity(&self) -> usize {
+        1
+    }
+}
+
+native_display!(NativeTrimEnd);
+
+pub struct NativeReplace;
+
+impl LoxCallable for NativeReplace {
+    fn call(&self, _interpreter: &Interpreter, arguments: Vec<Object>, klass: Option<Rc<Class>>) -> Result<Object, LoxResult> {
+        match (&arguments[0], &arguments[1], &arguments[2]) {
+            (Object::Str(_), Object::Str(pattern), Object::Str(_)) if pattern.is_empty() => {
+                Err(LoxResult::native_error("replace() pattern must not be empty."))
+            }
+            (Object::Str(s), Object::Str(pattern), Object::Str(replacement)) => {
+                Ok(Object::Str(s.replace(pattern.as_str(), replacement)))
+            }
+            _ => Err(LoxResult::native_error("replace() arguments must be strings.")),
+        }
+    }
+
+    fn arity(&self) -> usize {
+        3
+    }
+}
+
+native_display!(NativeReplace);
+
+pub struct NativeStartsWith;
+
+impl LoxCallable for NativeStartsWith {
+    fn call(&self, _interpreter: &Interpreter, arguments: Vec<Object>, klass: Option<Rc<Class>>) -> Result<Object, LoxResult> {
+        match (&arguments[0], &arguments[1]) {
+            (Object::Str(s), Object::Str(prefix)) => Ok(Object::Bool(s.starts_with(prefix.as_str()))),
+            _ => Err(LoxResult::native_error("starts_with() arguments must be strings.")),
+        }
+    }
+
+    fn arity(&self) -> usize {
+        2
+    }
+}
+
+native_display!(NativeStartsWith);
+
+pub struct NativeEndsWith;
+
+impl LoxCallable for NativeEndsWith {
+    fn call(&self, _interpreter: &Interpreter, arguments: Vec<Object>, klass: Option<Rc<Class>>) -> Result<Object, LoxResult> {
+        match (&arguments[0], &arguments[1]) {
+            (Object::Str(s), Object::Str(suffix)) => Ok(Object::Bool(s.ends_with(suffix.as_str()))),
+            _ => Err(LoxResult::native_error("ends_with() arguments must be strings.")),
+        }
+    }
+
+    fn arity(&self) -> usize {
+        2
+    }
+}
+
+native_display!(NativeEndsWith);
+
+pub struct NativeFloorMod;
+
+impl LoxCallable for NativeFloorMod {
+    // Unlike Rust's `%`, which takes the sign of the dividend, the result has the sign of the
+    // divisor: floor_mod(-1, 3) is 2, not -1.
+    fn call(&self, _interpreter: &Interpreter, arguments: Vec<Object>, klass: Option<Rc<Class>>) -> Result<Object, LoxResult> {
+        match (&arguments[0], &arguments[1]) {
+            (Object::Num(_), Object::Num(b)) if *b == 0.0 => {
+                Err(LoxResult::native_error("floor_mod() division by zero."))
+            }
+            (Object::Num(a), Object::Num(b)) => Ok(Object::Num(((a % b) + b) % b)),
+            _ => Err(LoxResult::native_error("floor_mod() operands must be numbers.")),
+        }
+    }
+
+    fn arity(&self) -> usize {
+        2
+    }
+}
+
+native_display!(NativeFloorMod);
+
+pub struct NativeReverse;
+
+impl LoxCallable for NativeReverse {
+    // Strings are reversed char by char, so a grapheme made of several chars (an accent that
+    // combines with the letter before it, a flag emoji) comes out broken up.
+    fn call(&self, _interpreter: &Interpreter, arguments: Vec<Object>, klass: Option<Rc<Class>>) -> Result<Object, LoxResult> {
+        match &arguments[0] {
+            Object::Array(array) => Ok(Object::array(array.borrow().iter().rev().cloned().collect())),
+            Object::Str(s) => Ok(Object::Str(s.chars().rev().collect())),
+            _ => Err(LoxResult::native_error("reverse() argument must be an array or a string.")),
+        }
+    }
+
+    fn arity(&self) -> usize {
+        1
+    }
+}
+
+native_display!(NativeReverse);
+
+pub struct NativeMapFromPairs;
+
+impl LoxCallable for NativeMapFromPairs {
+    // A later pair wins over an earlier one with the same key.
+    fn call(&self, _interpreter: &Interpreter, arguments: Vec<Object>, klass: Option<Rc<Class>>) -> Result<Object, LoxResult> {
+        let Object::Array(pairs) = &arguments[0] else {
+            return Err(LoxResult::native_error("map_from_pairs() argument must be an array."));
+        };
+
+        let mut map = HashMap::new();
+        for pair in pairs.borrow().iter() {
+            let Object::Array(pair) = pair else {
+                return Err(LoxResult::native_error(&format!(
+                    "map_from_pairs() '{}' is not a [key, value] pair.",
+                    pair
+                )));
+            };
+            match pair.borrow().as_slice() {
+                [Object::Str(key), value] => {
+                    map.insert(key.clone(), value.clone());
+                }
+                [key, _] => {
+                    return Err(LoxResult::native_error(&format!(
+                        "map_from_pairs() key '{}' is not a string.",
+                        key
+                    )))
+                }
+                _ => {
+                    return Err(LoxResult::native_error(&format!(
+                        "map_from_pairs() '{}' is not a [key, value] pair.",
+                        Object::Array(Rc::clone(pair))
+                    )))
+                }
+            }
+        }
+        Ok(Object::map(map))
+    }
+
+    fn arity(&self) -> usize {
+        1
+    }
+}
+
+native_display!(NativeMapFromPairs);
+
+pub struct NativeEntries;
+
+impl LoxCallable for NativeEntries {
+    // Sorted by key, so the result doesn't depend on the map's internal order.
+    fn call(&self, _interpreter: &Interpreter, arguments: Vec<Object>, klass: Option<Rc<Class>>) -> Result<Object, LoxResult> {
+        let Object::Map(map) = &arguments[0] else {
+            return Err(LoxResult::native_error("entries() argument must be a map."));
+        };
+
+        let mut entries = map
+            .borrow()
+            .iter()
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect::<Vec<(String, Object)>>();
+        entries.sort_by(|a, b| a.0.cmp(&b.0));
+
+        Ok(Object::array(
+            entries
+                .into_iter()
+                .map(|(key, value)| Object::array(vec![Object::Str(key), value]))
+                .collect(),
+        ))
+    }
+
+    fn arity(&self) -> usize {
+        1
+    }
+}
+
+native_display!(NativeEntries);
+
+pub struct NativeUnique;
+
+impl LoxCallable for NativeUnique {
+    // Values that can be hashed are looked up by their hash, everything else is compared against
+    // every unique element kept so far.
+    fn call(&self, _interpreter: &Interpreter, arguments: Vec<Object>, klass: Option<Rc<Class>>) -> Result<Object, LoxResult> {
+        let Object::Array(array) = &arguments[0] else {
+            return Err(LoxResult::native_error("unique() argument must be an array."));
+        };
+
+        let mut unique: Vec<Object> = Vec::new();
+        let mut seen: HashMap<u64, Vec<usize>> = HashMap::new();
+        for element in array.borrow().iter() {
+            let is_new = match element.stable_hash() {
+                Some(hash) => {
+                    let indices = seen.entry(hash).or_default();
+                    let is_new = !indices.iter().any(|&i| unique[i] == *element);
+                    if is_new {
+                        indices.push(unique.len());
+                    }
+                    is_new
+                }
+                None => !unique.contains(element),
+            };
+            if is_new {
+                unique.push(element.clone());
+            }
+        }
+        Ok(Object::array(unique))
+    }
+
+    fn arity(&self) -> usize {
+        1
+    }
+}
+
+native_display!(NativeUnique);
+
+pub struct NativeCompose;
+
+impl LoxCallable for NativeCompose {
+    fn call(&self, _interpreter: &Interpreter, arguments: Vec<Object>, klass: Option<Rc<Class>>) -> Result<Object, LoxResult> {
+        if as_callable(&arguments[0]).is_none() || as_callable(&arguments[1]).is_none() {
+            return Err(LoxResult::native_error("compose() arguments must be callable."));
+        }
+
+        Ok(Object::Native(Rc::new(Native::new(
+            "compose",
+            Rc::new(ComposedFunction {
+                outer: arguments[0].clone(),
+                inner: arguments[1].clone(),
+            }),
+        ))))
+    }
+
+    fn arity(&self) -> usize {
+        2
+    }
+}
+
+native_display!(NativeCompose);
+
+// The callable returned by `compose()`: calls `inner` with its argument and `outer` with the result.
+struct ComposedFunction {
+    outer: Object,
+    inner: Object,
+}
+
+impl LoxCallable for ComposedFunction {
+    fn call(&self, interpreter: &Interpreter, arguments: Vec<Object>, klass: Option<Rc<Class>>) -> Result<Object, LoxResult> {
+        let intermediate = interpreter.call_value(&self.inner, arguments)?;
+        interpreter.call_value(&self.outer, vec![intermediate])
+    }
+
+    fn arity(&self) -> usize {
+        1
+    }
+}
+
+impl fmt::Display for ComposedFunction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "<compose {} {}>", self.outer, self.inner)
+    }
+}
+
+pub struct NativePanic;
+
+impl LoxCallable for NativePanic {
+    // Always fails. The message comes with the calls that led here, `panic` itself included.
+    fn call(&self, interpreter: &Interpreter, arguments: Vec<Object>, klass: Option<Rc<Class>>) -> Result<Object, LoxResult> {
+        Err(LoxResult::native_error(&format!(
+            "panic() {}\n{}",
+            arguments[0],
+            interpreter.backtrace()
+        )))
+    }
+
+    fn arity(&self) -> usize {
+        1
+    }
+}
+
+native_display!(NativePanic);
+
+pub struct NativeEqualsIgnoreCase;
+
+impl LoxCallable for NativeEqualsIgnoreCase {
+    // Compares the Unicode lowercase forms. That is not full case folding: "straße" lowercases to
+    // itself and "STRASSE" to "strasse", so the two don't match.
+    fn call(&self, _interpreter: &Interpreter, arguments: Vec<Object>, klass: Option<Rc<Class>>) -> Result<Object, LoxResult> {
+        match (&arguments[0], &arguments[1]) {
+            (Object::Str(a), Object::Str(b)) => Ok(Object::Bool(a.to_lowercase() == b.to_lowercase())),
+            _ => Err(LoxResult::native_error("eq_ignore_case() arguments must be strings.")),
+        }
+    }
+
+    fn arity(&self) -> usize {
+        2
+    }
+}
+
+native_display!(NativeEqualsIgnoreCase);
+
+// Appends the elements of `array` to `flat`, splicing in the elements of nested arrays down to
+// `depth` levels. `visiting` holds the arrays being spliced further up, an array met again inside
+// itself is kept as an element rather than spliced into itself over and over.
+fn flatten_into(array: &Rc<RefCell<Vec<Object>>>, depth: usize, flat: &mut Vec<Object>, visiting: &mut Vec<*const RefCell<Vec<Object>>>) {
+    visiting.push(Rc::as_ptr(array));
+    for element in array.borrow().iter() {
+        match element {
+            Object::Array(nested) if depth > 0 && !visiting.contains(&Rc::as_ptr(nested)) => {
+                flatten_into(nested, depth - 1, flat, visiting)
+            }
+            _ => flat.push(element.clone()),
+        }
+    }
+    visiting.pop();
+}
+
+pub struct NativeFlatten;
+
+impl LoxCallable for NativeFlatten {
+    // Flattens a single level unless given a depth.
+    fn call(&self, _interpreter: &Interpreter, arguments: Vec<Object>, klass: Option<Rc<Class>>) -> Result<Object, LoxResult> {
+        let Object::Array(array) = &arguments[0] else {
+            return Err(LoxResult::native_error("flatten() first argument must be an array."));
+        };
+        let depth = match arguments.get(1) {
+            None => 1,
+            Some(Object::Num(n)) if *n >= 0.0 && n.fract() == 0.0 => *n as usize,
+            Some(_) => {
+                return Err(LoxResult::native_error(
+                    "flatten() depth must be a non-negative integer.",
+                ))
+            }
+        };
+        let mut flat = Vec::new();
+        flatten_into(array, depth, &mut flat, &mut Vec::new());
+        Ok(Object::array(flat))
+    }
+
+    fn arity(&self) -> usize {
+        2
+    }
+
+    fn min_arity(&self) -> usize {
+        1
+    }
+}
+
+native_display!(NativeFlatten);
+
+pub struct NativeRepeatString;
+
+impl LoxCallable for NativeRepeatString {
+    fn call(&self, _interpreter: &Interpreter, arguments: Vec<Object>, klass: Option<Rc<Class>>) -> Result<Object, LoxResult> {
+        let Object::Str(s) = &arguments[0] else {
+            return Err(LoxResult::native_error("repeat_string() first argument must be a string."));
+        };
+        let count = match arguments[1] {
+            Object::Num(n) if n >= 0.0 && n.fract() == 0.0 => n as usize,
+            _ => return Err(LoxResult::native_error("repeat_string() count must be a non-negative integer.")),
+        };
+        match s.len().checked_mul(count) {
+            Some(length) if length <= MAX_STRING_LENGTH => Ok(Object::Str(s.repeat(count))),
+            _ => Err(LoxResult::native_error("repeat_string() result would be too long.")),
+        }
+    }
+
+    fn arity(&self) -> usize {
+        2
+    }
+}
+
+native_display!(NativeRepeatString);
+
+pub struct NativeCount;
+
+impl LoxCallable for NativeCount {
+    fn call(&self, _interpreter: &Interpreter, arguments: Vec<Object>, klass: Option<Rc<Class>>) -> Result<Object, LoxResult> {
+        match (&arguments[0], &arguments[1]) {
+            (Object::Str(_), Object::Str(needle)) if needle.is_empty() => {
+                Err(LoxResult::native_error("count() substring must not be empty."))
+            }
+            (Object::Str(haystack), Object::Str(needle)) => {
+                Ok(Object::Num(haystack.matches(needle.as_str()).count() as f64))
+            }
+            (Object::Str(_), other) => Err(LoxResult::native_error(&format!(
+                "count() can't count '{}' in a string.",
+                other
+            ))),
+            (Object::Array(array), element) => {
+                Ok(Object::Num(array.borrow().iter().filter(|e| *e == element).count() as f64))
+            }
+            _ => Err(LoxResult::native_error("count() first argument must be a string or an array.")),
+        }
+    }
+
+    fn arity(&self) -> usize {
+        2
+    }
+}
+
+native_display!(NativeCount);
+
+// The (year, month, day) of a day counted from 1970-01-01, in the proleptic Gregorian calendar.
+// This is Howard Hinnant's `civil_from_days`.
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let day_of_era = z.rem_euclid(146097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+const MAX_TIMESTAMP: f64 = 8.64e15;
+
+pub struct NativeDateParts;
+
+impl LoxCallable for NativeDateParts {
+    // Takes milliseconds since the Unix epoch, like `clock()` returns, and splits them up in UTC.
+    fn call(&self, _interpreter: &Interpreter, arguments: Vec<Object>, klass: Option<Rc<Class>>) -> Result<Object, LoxResult> {
+        let millis = match arguments[0] {
+            Object::Num(n) if n.is_finite() => n,
+            _ => return Err(LoxResult::native_error("date_parts() argument must be a finite number.")),
+        };
+        // the same range as a JavaScript `Date`, 100 million days either side of the epoch, so
+        // the seconds fit an `i64` and the day math can't overflow
+        if millis.abs() > MAX_TIMESTAMP {
+            return Err(LoxResult::native_error(
+                "date_parts() timestamp must be within 8.64e15 milliseconds of the epoch.",
+            ));
+        }
+
+        let seconds = (millis / 1000.0).floor() as i64;
+        let (year, month, day) = civil_from_days(seconds.div_euclid(86400));
+        let second_of_day = seconds.rem_euclid(86400);
+
+        let parts = [
+            ("year", year),
+            ("month", month),
+            ("day", day),
+            ("hour", second_of_day / 3600),
+            ("minute", second_of_day % 3600 / 60),
+            ("second", second_of_day % 60),
+        ];
+        Ok(Object::map(
+            parts
+                .into_iter()
+                .map(|(name, value)| (name.to_string(), Object::Num(value as f64)))
+                .collect(),
+        ))
+    }
+
+    fn arity(&self) -> usize {
+        1
+    }
+}
+
+native_display!(NativeDateParts);
+
+pub struct NativeToFixed;
+
+impl LoxCallable for NativeToFixed {
+    fn call(&self, _interpreter: &Interpreter, arguments: Vec<Object>, klass: Option<Rc<Class>>) -> Result<Object, LoxResult> {
+        let Object::Num(n) = arguments[0] else {
+            return Err(LoxResult::native_error("to_fixed() first argument must be a number."));
+        };
+        // the same range as `toFixed` in JavaScript
+        match arguments[1] {
+            Object::Num(digits) if (0.0..=100.0).contains(&digits) && digits.fract() == 0.0 => {
+                Ok(Object::Str(format!("{:.*}", digits as usize, n)))
+            }
+            _ => Err(LoxResult::native_error("to_fixed() digits must be an integer between 0 and 100.")),
+        }
+    }
+
+    fn arity(&self) -> usize {
+        2
+    }
+}
+
+native_display!(NativeToFixed);
+
+pub struct NativeSwap;
+
+impl LoxCallable for NativeSwap {
+    // Swaps in place, so everything holding on to the array sees the change.
+    fn call(&self, _interpreter: &Interpreter, arguments: Vec<Object>, klass: Option<Rc<Class>>) -> Result<Object, LoxResult> {
+        let Object::Array(array) = &arguments[0] else {
+            return Err(LoxResult::native_error("swap() first argument must be an array."));
+        };
+
+        let len = array.borrow().len();
+        let index = |value: &Object| match *value {
+            Object::Num(n) if n >= 0.0 && n.fract() == 0.0 && (n as usize) < len => Ok(n as usize),
+            Object::Num(n) if n.fract() == 0.0 => Err(LoxResult::native_error(&format!(
+                "swap() index {} is out of bounds for length {}.",
+                n, len
+            ))),
+            _ => Err(LoxResult::native_error("swap() indices must be integers.")),
+        };
+        let (i, j) = (index(&arguments[1])?, index(&arguments[2])?);
+
+        array.borrow_mut().swap(i, j);
+        Ok(arguments[0].clone())
+    }
+
+    fn arity(&self) -> usize {
+        3
+    }
+}
+
+native_display!(NativeSwap);
+
+pub struct NativeMapGet;
+
+impl LoxCallable for NativeMapGet {
+    fn call(&self, _interpreter: &Interpreter, arguments: Vec<Object>, klass: Option<Rc<Class>>) -> Result<Object, LoxResult> {
+        let Object::Map(map) = &arguments[0] else {
+            return Err(LoxResult::native_error("map_get() first argument must be a map."));
+        };
+        let Object::Str(key) = &arguments[1] else {
+            return Err(LoxResult::native_error(&format!(
+                "map_get() key '{}' is not a string.",
+                arguments[1]
+            )));
+        };
+        Ok(map.borrow().get(key).cloned().unwrap_or_else(|| arguments[2].clone()))
+    }
+
+    fn arity(&self) -> usize {
+        3
+    }
+}
+
+native_display!(NativeMapGet);
+
+// Converts a value to "number", "string" or "boolean":
+//   string -> number   parses the string, errors if it isn't a number
+//   number -> string   same text `print` shows
+//   string -> boolean  only "true" and "false"
+//   any    -> boolean  truthiness, like in an `if` condition
+// Converting to the type a value already has returns it unchanged, everything else is an error.
+pub struct NativeConvert;
+
+impl LoxCallable for NativeConvert {
+    fn call(&self, _interpreter: &Interpreter, arguments: Vec<Object>, klass: Option<Rc<Class>>) -> Result<Object, LoxResult> {
+        let Object::Str(target) = &arguments[1] else {
+            return Err(LoxResult::native_error("convert() target type must be a string."));
+        };
+        let value = &arguments[0];
+        match (value, target.as_str()) {
+            (Object::Num(_), "number") | (Object::Str(_), "string") | (Object::Bool(_), "boolean") => {
+                Ok(value.clone())
+            }
+            (Object::Str(s), "number") => s.trim().parse::<f64>().map(Object::Num).map_err(|_| {
+                LoxResult::native_error(&format!("convert() can't convert \"{}\" to a number.", s))
+            }),
+            (Object::Num(_), "string") => Ok(Object::Str(value.to_string())),
+            (Object::Str(s), "boolean") => match s.as_str() {
+                "true" => Ok(Object::Bool(true)),
+                "false" => Ok(Object::Bool(false)),
+                _ => Err(LoxResult::native_error(&format!(
+                    "convert() can't convert \"{}\" to a boolean.",
+                    s
+                ))),
+            },
+            (_, "boolean") => Ok(Object::Bool(!matches!(value, Object::Nil | Object::Bool(false)))),
+            (_, "number" | "string") => Err(LoxResult::native_error(&format!(
+                "convert() can't convert {} to a {}.",
+                value, target
+            ))),
+            _ => Err(LoxResult::native_error(&format!(
+                "convert() unknown target type \"{}\".",
+                target
+            ))),
+        }
+    }
+
+    fn arity(&self) -> usize {
+        2
+    }
+}
+
+native_display!(NativeConvert);
+
+// Backs `is_number`, `is_string` and the other type checks, which only differ in which values they
+// accept.
+pub struct NativeTypePredicate {
+    test: fn(&Object) -> bool,
+}
+
+impl NativeTypePredicate {
+    pub fn new(test: fn(&Object) -> bool) -> Self {
+        NativeTypePredicate { test }
+    }
+}
+
+impl LoxCallable for NativeTypePredicate {
+    fn call(&self, _interpreter: &Interpreter, arguments: Vec<Object>, klass: Option<Rc<Class>>) -> Result<Object, LoxResult> {
+        Ok(Object::Bool((self.test)(&arguments[0])))
+    }
+
+    fn arity(&self) -> usize {
+        1
+    }
+}
+
+native_display!(NativeTypePredicate);
+
+pub struct NativeClampIndex;
+
+impl LoxCallable for NativeClampIndex {
+    // Negative indices count from the end like -1 for the last element, anything still outside
+    // the array afterwards is moved to the nearest end.
+    fn call(&self, _interpreter: &Interpreter, arguments: Vec<Object>, klass: Option<Rc<Class>>) -> Result<Object, LoxResult> {
+        let (Object::Num(index), Object::Num(len)) = (&arguments[0], &arguments[1]) else {
+            return Err(LoxResult::native_error("clamp_index() arguments must be integers."));
+        };
+        if index.fract() != 0.0 || len.fract() != 0.0 {
+            return Err(LoxResult::native_error("clamp_index() arguments must be integers."));
+        }
+        if *len < 1.0 {
+            return Err(LoxResult::native_error("clamp_index() length must be at least 1."));
+        }
+
+        let index = if *index < 0.0 { index + len } else { *index };
+        Ok(Object::Num(index.clamp(0.0, len - 1.0)))
+    }
+
+    fn arity(&self) -> usize {
+        2
+    }
+}
+
+native_display!(NativeClampIndex);
+
+// The padding `pad_start()`/`pad_end()` add to the string in `arguments`: the pad string repeated
+// and cut off so the result is exactly the target length, counted in characters. Empty if the
+// string is already long enough.
+fn padding(name: &str, arguments: &[Object]) -> Result<String, LoxResult> {
+    let Object::Str(s) = &arguments[0] else {
+        return Err(LoxResult::native_error(&format!("{}() first argument must be a string.", name)));
+    };
+    let Object::Num(width) = arguments[1] else {
+        return Err(LoxResult::native_error(&format!("{}() length must be a non-negative integer.", name)));
+    };
+    if width < 0.0 || width.fract() != 0.0 {
+        return Err(LoxResult::native_error(&format!("{}() length must be a non-negative integer.", name)));
+    }
+    let pad = match &arguments[2] {
+        Object::Str(pad) if !pad.is_empty() => pad,
+        _ => return Err(LoxResult::native_error(&format!("{}() pad must be a non-empty string.", name))),
+    };
+    // checked before `width` is cast, that would saturate instead of failing
+    let widest_char = pad.chars().map(char::len_utf8).max().unwrap_or(1);
+    if width * widest_char as f64 > MAX_STRING_LENGTH as f64 {
+        return Err(LoxResult::native_error(&format!("{}() length {} is too long.", name, width)));
+    }
+
+    let missing = (width as usize).saturating_sub(s.chars().count());
+    Ok(pad.chars().cycle().take(missing).collect())
+}
+
+pub struct NativePadStart;
+
+impl LoxCallable for NativePadStart {
+    fn call(&self, _interpreter: &Interpreter, arguments: Vec<Object>, klass: Option<Rc<Class>>) -> Result<Object, LoxResult> {
+        let padding = padding("pad_start", &arguments)?;
+        Ok(Object::Str(format!("{}{}", padding, arguments[0])))
+    }
+
+    fn arity(&self) -> usize {
+        3
+    }
+}
+
+native_display!(NativePadStart);
+
+pub struct NativePadEnd;
+
+impl LoxCallable for NativePadEnd {
+    fn call(&self, _interpreter: &Interpreter, arguments: Vec<Object>, klass: Option<Rc<Class>>) -> Result<Object, LoxResult> {
+        let padding = padding("pad_end", &arguments)?;
+        Ok(Object::Str(format!("{}{}", arguments[0], padding)))
+    }
+
+    fn arity(&self) -> usize {
+        3
+    }
+}
+
+native_display!(NativePadEnd);
+
+// Shared by `max_by()` and `min_by()`: the element whose key compares as `wanted` against every
+// other key. On ties the first such element wins.
+fn extreme_by(
+    name: &str,
+    interpreter: &Interpreter,
+    arguments: &[Object],
+    wanted: Ordering,
+) -> Result<Object, LoxResult> {
+    let Object::Array(array) = &arguments[0] else {
+        return Err(LoxResult::native_error(&format!("{}() first argument must be an array.", name)));
+    };
+
+    let elements = snapshot(array);
+    let mut best: Option<(Object, Object)> = None;
+    for element in elements {
+        let key = interpreter.call_value(&arguments[1], vec![element.clone()])?;
+        let replace = match &best {
+            None => true,
+            Some((_, best_key)) => match key.partial_cmp(best_key) {
+                Some(ordering) => ordering == wanted,
+                None => {
+                    return Err(LoxResult::native_error(&format!(
+                        "{}() can't compare keys '{}' and '{}'.",
+                        name, key, best_key
+                    )))
+                }
+            },
+        };
+        if replace {
+            best = Some((element, key));
+        }
+    }
+
+    best.map(|(element, _)| element)
+        .ok_or_else(|| LoxResult::native_error(&format!("{}() array is empty.", name)))
+}
+
+pub struct NativeMaxBy;
+
+impl LoxCallable for NativeMaxBy {
+    fn call(&self, interpreter: &Interpreter, arguments: Vec<Object>, klass: Option<Rc<Class>>) -> Result<Object, LoxResult> {
+        extreme_by("max_by", interpreter, &arguments, Ordering::Greater)
+    }
+
+    fn arity(&self) -> usize {
+        2
+    }
+}
+
+native_display!(NativeMaxBy);
+
+pub struct NativeMinBy;
+
+impl LoxCallable for NativeMinBy {
+    fn call(&self, interpreter: &Interpreter, arguments: Vec<Object>, klass: Option<Rc<Class>>) -> Result<Object, LoxResult> {
+        extreme_by("min_by", interpreter, &arguments, Ordering::Less)
+    }
+
+    fn arity(&self) -> usize {
+        2
+    }
+}
+
+native_display!(NativeMinBy);
+
+pub struct NativeLen;
+
+impl LoxCallable for NativeLen {
+    // Strings are measured in characters, not bytes.
+    fn call(&self, _interpreter: &Interpreter, arguments: Vec<Object>, klass: Option<Rc<Class>>) -> Result<Object, LoxResult> {
+        match &arguments[0] {
+            Object::Str(s) => Ok(Object::Num(s.chars().count() as f64)),
+            Object::Array(array) => Ok(Object::Num(array.borrow().len() as f64)),
+            Object::Map(map) => Ok(Object::Num(map.borrow().len() as f64)),
+            other => Err(LoxResult::native_error(&format!("len() '{}' has no length.", other))),
+        }
+    }
+
+    fn arity(&self) -> usize {
+        1
+    }
+}
+
+native_display!(NativeLen);
+
+pub struct NativePush;
+
+impl LoxCallable for NativePush {
+    // Appends in place and returns the array, so pushes can be chained.
+    fn call(&self, _interpreter: &Interpreter, arguments: Vec<Object>, klass: Option<Rc<Class>>) -> Result<Object, LoxResult> {
+        let Object::Array(array) = &arguments[0] else {
+            return Err(LoxResult::native_error("push() first argument must be an array."));
+        };
+        array.borrow_mut().push(arguments[1].clone());
+        Ok(arguments[0].clone())
+    }
+
+    fn arity(&self) -> usize {
+        2
+    }
+}
+
+native_display!(NativePush);
+
+pub struct NativePop;
+
+impl LoxCallable for NativePop {
+    fn call(&self, _interpreter: &Interpreter, arguments: Vec<Object>, klass: Option<Rc<Class>>) -> Result<Object, LoxResult> {
+        let Object::Array(array) = &arguments[0] else {
+            return Err(LoxResult::native_error("pop() argument must be an array."));
+        };
+        array
+            .borrow_mut()
+            .pop()
+            .ok_or_else(|| LoxResult::native_error("pop() array is empty."))
+    }
+
+    fn arity(&self) -> usize {
+        1
+    }
+}
+
+native_display!(NativePop);
+
+pub struct NativeKeys;
+
+impl LoxCallable for NativeKeys {
+    // Sorted, so the result doesn't depend on the map's internal order.
+    fn call(&self, _interpreter: &Interpreter, arguments: Vec<Object>, klass: Option<Rc<Class>>) -> Result<Object, LoxResult> {
+        let Object::Map(map) = &arguments[0] else {
+            return Err(LoxResult::native_error("keys() argument must be a map."));
+        };
+        let mut keys = map.borrow().keys().cloned().collect::<Vec<String>>();
+        keys.sort();
+        Ok(Object::array(keys.into_iter().map(Object::Str).collect()))
+    }
+
+    fn arity(&self) -> usize {
+        1
+    }
+}
+
+native_display!(NativeKeys);
+
+pub struct NativeHas;
+
+impl LoxCallable for NativeHas {
+    fn call(&self, _interpreter: &Interpreter, arguments: Vec<Object>, klass: Option<Rc<Class>>) -> Result<Object, LoxResult> {
+        let Object::Map(map) = &arguments[0] else {
+            return Err(LoxResult::native_error("has() first argument must be a map."));
+        };
+        let Object::Str(key) = &arguments[1] else {
+            return Err(LoxResult::native_error(&format!("has() key '{}' is not a string.", arguments[1])));
+        };
+        Ok(Object::Bool(map.borrow().contains_key(key)))
+    }
+
+    fn arity(&self) -> usize {
+        2
+    }
+}
+
+native_display!(NativeHas);
+
+pub struct NativeRandom;
+
+impl LoxCallable for NativeRandom {
+    // A number in `[0, 1)`.
+    fn call(&self, interpreter: &Interpreter, _arguments: Vec<Object>, klass: Option<Rc<Class>>) -> Result<Object, LoxResult> {
+        Ok(Object::Num(interpreter.rng().next_f64()))
+    }
+
+    fn arity(&self) -> usize {
+        0
+    }
+}
+
+native_display!(NativeRandom);
+
+pub struct NativeSeed;
+
+impl LoxCallable for NativeSeed {
+    fn call(&self, interpreter: &Interpreter, arguments: Vec<Object>, klass: Option<Rc<Class>>) -> Result<Object, LoxResult> {
+        match arguments[0] {
+            Object::Num(n) if n >= 0.0 && n.fract() == 0.0 => {
+                *interpreter.rng() = Rng::new(n as u64);
+                Ok(Object::Nil)
+            }
+            _ => Err(LoxResult::native_error("seed() argument must be a non-negative integer.")),
+        }
+    }
+
+    fn arity(&self) -> usize {
+        1
+    }
+}
+
+native_display!(NativeSeed);
+
+pub struct NativeShuffle;
+
+impl LoxCallable for NativeShuffle {
+    // Fisher-Yates on a copy, the array passed in stays as it is.
+    fn call(&self, interpreter: &Interpreter, arguments: Vec<Object>, klass: Option<Rc<Class>>) -> Result<Object, LoxResult> {
+        let Object::Array(array) = &arguments[0] else {
+            return Err(LoxResult::native_error("shuffle() argument must be an array."));
+        };
+
+        let mut shuffled = array.borrow().clone();
+        let mut rng = interpreter.rng();
+        for i in (1..shuffled.len()).rev() {
+            shuffled.swap(i, rng.below(i + 1));
+        }
+        Ok(Object::array(shuffled))
+    }
+
+    fn arity(&self) -> usize {
+        1
+    }
+}
+
+native_display!(NativeShuffle);
+
+pub struct NativeSample;
+
+impl LoxCallable for NativeSample {
+    // The first `count` steps of the Fisher-Yates shuffle in `shuffle()`, so every element is
+    // picked at most once.
+    fn call(&self, interpreter: &Interpreter, arguments: Vec<Object>, klass: Option<Rc<Class>>) -> Result<Object, LoxResult> {
+        let Object::Array(array) = &arguments[0] else {
+            return Err(LoxResult::native_error("sample() first argument must be an array."));
+        };
+        let mut pool = array.borrow().clone();
+        let count = match arguments[1] {
+            Object::Num(n) if n >= 0.0 && n.fract() == 0.0 => n,
+            _ => return Err(LoxResult::native_error("sample() count must be a non-negative integer.")),
+        };
+        // compared before converting, a count too large for `usize` would saturate
+        if count > pool.len() as f64 {
+            return Err(LoxResult::native_error(&format!(
+                "sample() count {} is larger than the array length {}.",
+                count,
+                pool.len()
+            )));
+        }
+        let count = count as usize;
+
+        let mut rng = interpreter.rng();
+        for i in 0..count {
+            let j = i + rng.below(pool.len() - i);
+            pool.swap(i, j);
+        }
+        pool.truncate(count);
+        Ok(Object::array(pool))
+    }
+
+    fn arity(&self) -> usize {
+        2
+    }
+}
+
+native_display!(NativeSample);
+
+pub struct NativeBindThis;
+
+impl LoxCallable for NativeBindThis {
+    // Only methods can be bound, they are the only functions that can use `this`. A method read
+    // from an instance is already bound to it, binding it again swaps the instance.
+    fn call(&self, _interpreter: &Interpreter, arguments: Vec<Object>, klass: Option<Rc<Class>>) -> Result<Object, LoxResult> {
+        let method = match &arguments[0] {
+            Object::Func(method) if method.is_bound() => method,
+            _ => return Err(LoxResult::native_error("bind() first argument must be a method.")),
+        };
+        if !matches!(arguments[1], Object::Instance(_)) {
+            return Err(LoxResult::native_error("bind() second argument must be an instance."));
+        }
+        Ok(method.bind(&arguments[1]))
+    }
+
+    fn arity(&self) -> usize {
+        2
+    }
+}
+
+native_display!(NativeBindThis);
+
+pub struct NativeExpect;
+
+impl LoxCallable for NativeExpect {
+    fn call(&self, _interpreter: &Interpreter, arguments: Vec<Object>, klass: Option<Rc<Class>>) -> Result<Object, LoxResult> {
+        let message = match &arguments[1] {
+            Object::Str(message) => message,
+            _ => return Err(LoxResult::native_error("expect() second argument must be a string.")),
+        };
+        match &arguments[0] {
+            Object::Nil => Err(LoxResult::native_error(message)),
+            value => Ok(value.clone()),
+        }
+    }
+
+    fn arity(&self) -> usize {
+        2
+    }
+}
+
+native_display!(NativeExpect);
+
+pub struct NativeFields;
+
+impl LoxCallable for NativeFields {
+    // In the order the fields were first set.
+    fn call(&self, _interpreter: &Interpreter, arguments: Vec<Object>, klass: Option<Rc<Class>>) -> Result<Object, LoxResult> {
+        let Object::Instance(instance) = &arguments[0] else {
+            return Err(LoxResult::native_error("fields() argument must be an instance."));
+        };
+        let names = instance.fields().into_iter().map(|(name, _)| Object::Str(name));
+        Ok(Object::array(names.collect()))
+    }
+
+    fn arity(&self) -> usize {
+        1
+    }
+}
+
+native_display!(NativeFields);
+
+// Accepts a class or an instance, which stands for its class.
+fn class_argument(name: &str, argument: &Object) -> Result<Rc<Class>, LoxResult> {
+    match argument {
+        Object::Class(klass) => Ok(Rc::clone(klass)),
+        Object::Instance(instance) => Ok(Rc::clone(instance.class())),
+        _ => Err(LoxResult::native_error(&format!("{name}() argument must be a class or an instance."))),
+    }
+}
+
+pub struct NativeClassName;
+
+impl LoxCallable for NativeClassName {
+    fn call(&self, _interpreter: &Interpreter, arguments: Vec<Object>, klass: Option<Rc<Class>>) -> Result<Object, LoxResult> {
+        let klass = class_argument("class_name", &arguments[0])?;
+        Ok(Object::Str(klass.name().to_string()))
+    }
+
+    fn arity(&self) -> usize {
+        1
+    }
+}
+
+native_display!(NativeClassName);
+
+pub struct NativeMethodNames;
+
+impl LoxCallable for NativeMethodNames {
+    fn call(&self, _interpreter: &Interpreter, arguments: Vec<Object>, klass: Option<Rc<Class>>) -> Result<Object, LoxResult> {
+        let klass = class_argument("method_names", &arguments[0])?;
+        Ok(Object::array(klass.method_names().into_iter().map(Object::Str).collect()))
+    }
+
+    fn arity(&self) -> usize {
+        1
+    }
+}
+
+native_display!(NativeMethodNames);
+
+pub struct NativeSqrt;
+
+impl LoxCallable for NativeSqrt {
+    fn call(&self, _interpreter: &Interpreter, arguments: Vec<Object>, klass: Option<Rc<Class>>) -> Result<Object, LoxResult> {
+        match arguments[0] {
+            Object::Num(n) if n < 0.0 => Err(LoxResult::native_error(&format!("sqrt() argument {} is negative.", n))),
+            Object::Num(n) => Ok(Object::Num(n.sqrt())),
+            _ => Err(LoxResult::native_error("sqrt() argument must be a number.")),
+        }
+    }
+
+    fn arity(&self) -> usize {
+        1
+    }
+}
+
+native_display!(NativeSqrt);
+
+pub struct NativePow;
+
+impl LoxCallable for NativePow {
+    fn call(&self, _interpreter: &Interpreter, arguments: Vec<Object>, klass: Option<Rc<Class>>) -> Result<Object, LoxResult> {
+        match (&arguments[0], &arguments[1]) {
+            (Object::Num(base), Object::Num(exponent)) => Ok(Object::Num(base.powf(*exponent))),
+            _ => Err(LoxResult::native_error("pow() arguments must be numbers.")),
+        }
+    }
+
+    fn arity(&self) -> usize {
+        2
+    }
+}
+
+native_display!(NativePow);
+
+pub struct NativeFloor;
+
+impl LoxCallable for NativeFloor {
+    fn call(&self, _interpreter: &Interpreter, arguments: Vec<Object>, klass: Option<Rc<Class>>) -> Result<Object, LoxResult> {
+        match arguments[0] {
+            Object::Num(n) => Ok(Object::Num(n.floor())),
+            _ => Err(LoxResult::native_error("floor() argument must be a number.")),
+        }
+    }
+
+    fn arity(&self) -> usize {
+        1
+    }
+}
+
+native_display!(NativeFloor);
+
+pub struct NativeCeil;
+
+impl LoxCallable for NativeCeil {
+    fn call(&self, _interpreter: &Interpreter, arguments: Vec<Object>, klass: Option<Rc<Class>>) -> Result<Object, LoxResult> {
+        match arguments[0] {
+            Object::Num(n) => Ok(Object::Num(n.ceil())),
+            _ => Err(LoxResult::native_error("ceil() argument must be a number.")),
+        }
+    }
+
+    fn arity(&self) -> usize {
+        1
+    }
+}
+
+native_display!(NativeCeil);
+
+pub struct NativeAbs;
+
+impl LoxCallable for NativeAbs {
+    fn call(&self, _interpreter: &Interpreter, arguments: Vec<Object>, klass: Option<Rc<Class>>) -> Result<Object, LoxResult> {
+        match arguments[0] {
+            Object::Num(n) => Ok(Object::Num(n.abs())),
+            _ => Err(LoxResult::native_error("abs() argument must be a number.")),
+        }
+    }
+
+    fn arity(&self) -> usize {
+        1
+    }
+}
+
+native_display!(NativeAbs);
+
+pub struct NativeMapMerge;
+
+impl LoxCallable for NativeMapMerge {
+    // Returns a new map, neither argument is changed. The second map wins on keys in both.
+    fn call(&self, _interpreter: &Interpreter, arguments: Vec<Object>, klass: Option<Rc<Class>>) -> Result<Object, LoxResult> {
+        let (Object::Map(first), Object::Map(second)) = (&arguments[0], &arguments[1]) else {
+            return Err(LoxResult::native_error("map_merge() arguments must be maps."));
+        };
+        let mut merged = first.borrow().clone();
+        merged.extend(second.borrow().iter().map(|(k, v)| (k.clone(), v.clone())));
+        Ok(Object::map(merged))
+    }
+
+    fn arity(&self) -> usize {
+        2
+    }
+}
+
+native_display!(NativeMapMerge);
+
+pub struct NativeSubstring;
+
+impl LoxCallable for NativeSubstring {
+    // Indices count characters and `end` is exclusive. They have to be integers with
+    // `0 <= start <= end <= len(s)`.
+    fn call(&self, _interpreter: &Interpreter, arguments: Vec<Object>, klass: Option<Rc<Class>>) -> Result<Object, LoxResult> {
+        let Object::Str(s) = &arguments[0] else {
+            return Err(LoxResult::native_error("substring() first argument must be a string."));
+        };
+        let (Object::Num(start), Object::Num(end)) = (&arguments[1], &arguments[2]) else {
+            return Err(LoxResult::native_error("substring() indices must be numbers."));
+        };
+        for index in [start, end] {
+            if *index < 0.0 || index.fract() != 0.0 {
+                return Err(LoxResult::native_error(&format!(
+                    "substring() index {} must be a non-negative integer.",
+                    index
+                )));
+            }
+        }
+        if start > end {
+            return Err(LoxResult::native_error(&format!(
+                "substring() start {} is after end {}.",
+                start, end
+            )));
+        }
+        let len = s.chars().count();
+        if *end > len as f64 {
+            return Err(LoxResult::native_error(&format!(
+                "substring() end {} is past the end of a string of length {}.",
+                end, len
+            )));
+        }
+        let substring = s.chars().skip(*start as usize).take((end - start) as usize).collect();
+        Ok(Object::Str(substring))
+    }
+
+    fn arity(&self) -> usize {
+        3
+    }
+}
+
+native_display!(NativeSubstring);
+
+pub struct NativeUpper;
+
+impl LoxCallable for NativeUpper {
+    fn call(&self, _interpreter: &Interpreter, arguments: Vec<Object>, klass: Option<Rc<Class>>) -> Result<Object, LoxResult> {
+        match &arguments[0] {
+            Object::Str(s) => Ok(Object::Str(s.to_uppercase())),
+            _ => Err(LoxResult::native_error("upper() argument must be a string.")),
+        }
+    }
+
+    fn arity(&self) -> usize {
+        1
+    }
+}
+
+native_display!(NativeUpper);
+
+pub struct NativeLower;
+
+impl LoxCallable for NativeLower {
+    fn call(&self, _interpreter: &Interpreter, arguments: Vec<Object>, klass: Option<Rc<Class>>) -> Result<Object, LoxResult> {
+        match &arguments[0] {
+            Object::Str(s) => Ok(Object::Str(s.to_lowercase())),
+            _ => Err(LoxResult::native_error("lower() argument must be a string.")),
+        }
+    }
+
+    fn arity(&self) -> usize {
+        1
+    }
+}
+
+native_display!(NativeLower);
+
+pub struct NativeType;
+
+impl LoxCallable for NativeType {
+    // Natives count as functions, the same as for `is_function`.
+    fn call(&self, _interpreter: &Interpreter, arguments: Vec<Object>, klass: Option<Rc<Class>>) -> Result<Object, LoxResult> {
+        let name = match arguments[0] {
+            Object::Num(_) => "number",
+            Object::Str(_) => "string",
+            Object::Bool(_) => "boolean",
+            Object::Nil => "nil",
+            Object::Func(_) | Object::Native(_) => "function",
+            Object::Class(_) => "class",
+            Object::Instance(_) => "instance",
+            Object::Array(_) => "array",
+            Object::Map(_) => "map",
+            Object::StringBuilder(_) => "string_builder",
+            Object::ArithmeticError | Object::DivByZeroError => "error",
+        };
+        Ok(Object::Str(name.to_string()))
+    }
+
+    fn arity(&self) -> usize {
+        1
+    }
+}
+
+native_display!(NativeType);
+
+pub struct NativeZipWith;
+
+impl LoxCallable for NativeZipWith {
+    // Stops at the end of the shorter array.
+    fn call(&self, interpreter: &Interpreter, arguments: Vec<Object>, klass: Option<Rc<Class>>) -> Result<Object, LoxResult> {
+        let (Object::Array(first), Object::Array(second)) = (&arguments[0], &arguments[1]) else {
+            return Err(LoxResult::native_error("zip_with() first two arguments must be arrays."));
+        };
+        if as_callable(&arguments[2]).is_none() {
+            return Err(LoxResult::native_error("zip_with() third argument must be callable."));
+        }
+
+        let (first, second) = (snapshot(first), snapshot(second));
+        let zipped = first
+            .into_iter()
+            .zip(second)
+            .map(|(a, b)| interpreter.call_value(&arguments[2], vec![a, b]))
+            .collect::<Result<Vec<Object>, LoxResult>>()?;
+        Ok(Object::array(zipped))
+    }
+
+    fn arity(&self) -> usize {
+        3
+    }
+}
+
+native_display!(NativeZipWith);
+
+pub struct NativeStringBuilder;
+
+impl LoxCallable for NativeStringBuilder {
+    fn call(&self, _interpreter: &Interpreter, arguments: Vec<Object>, klass: Option<Rc<Class>>) -> Result<Object, LoxResult> {
+        Ok(Object::string_builder())
+    }
+
+    fn arity(&self) -> usize {
+        0
+    }
+}
+
+native_display!(NativeStringBuilder);
+
+pub struct NativeStringBuilderAppend;
+
+impl LoxCallable for NativeStringBuilderAppend {
+    // Appends the value the way `print` shows it and returns the builder, so calls can be chained.
+    fn call(&self, _interpreter: &Interpreter, arguments: Vec<Object>, klass: Option<Rc<Class>>) -> Result<Object, LoxResult> {
+        let Object::StringBuilder(builder) = &arguments[0] else {
+            return Err(LoxResult::native_error("sb_append() first argument must be a string builder."));
+        };
+        builder.borrow_mut().push_str(&arguments[1].to_string());
+        Ok(arguments[0].clone())
+    }
+
+    fn arity(&self) -> usize {
+        2
+    }
+}
+
+native_display!(NativeStringBuilderAppend);
+
+pub struct NativeStringBuilderBuild;
+
+impl LoxCallable for NativeStringBuilderBuild {
+    // The builder can still be appended to afterwards, that doesn't change the returned string.
+    fn call(&self, _interpreter: &Interpreter, arguments: Vec<Object>, klass: Option<Rc<Class>>) -> Result<Object, LoxResult> {
+        let Object::StringBuilder(builder) = &arguments[0] else {
+            return Err(LoxResult::native_error("sb_build() argument must be a string builder."));
+        };
+        Ok(Object::Str(builder.borrow().clone()))
+    }
+
+    fn arity(&self) -> usize {
+        1
+    }
+}
+
+native_display!(NativeStringBuilderBuild);
+
+pub struct NativeCharAt;
+
+impl LoxCallable for NativeCharAt {
+    // The index counts characters, not bytes, so a multi-byte character is never cut in half.
+    fn call(&self, _interpreter: &Interpreter, arguments: Vec<Object>, klass: Option<Rc<Class>>) -> Result<Object, LoxResult> {
+        let Object::Str(s) = &arguments[0] else {
+            return Err(LoxResult::native_error("char_at() first argument must be a string."));
+        };
+        let Object::Num(index) = arguments[1] else {
+            return Err(LoxResult::native_error("char_at() index must be a number."));
+        };
+        if index < 0.0 || index.fract() != 0.0 {
+            return Err(LoxResult::native_error(&format!(
+                "char_at() index {} must be a non-negative integer.",
+                index
+            )));
+        }
+        match s.chars().nth(index as usize) {
+            Some(c) => Ok(Object::Str(c.to_string())),
+            None => Err(LoxResult::native_error(&format!(
+                "char_at() index {} is out of bounds for length {}.",
+                index,
+                s.chars().count()
+            ))),
+        }
+    }
+
+    fn arity(&self) -> usize {
+        2
+    }
+}
+
+native_display!(NativeCharAt);
+
+pub struct NativeAssert;
+
+impl LoxCallable for NativeAssert {
+    // `assert(condition)` or `assert(condition, message)`. The error is raised as a native error
+    // so it gets reported at the call.
+    fn call(&self, interpreter: &Interpreter, arguments: Vec<Object>, klass: Option<Rc<Class>>) -> Result<Object, LoxResult> {
+        if interpreter.is_truthy(&arguments[0]) {
+            return Ok(Object::Nil);
+        }
+        match arguments.get(1) {
+            Some(message) => Err(LoxResult::native_error(&message.to_string())),
+            None => Err(LoxResult::native_error("Assertion failed.")),
+        }
+    }
+
+    fn arity(&self) -> usize {
+        2
+    }
+
+    fn min_arity(&self) -> usize {
+        1
+    }
+}
+
+native_display!(NativeAssert);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+
+    fn call(func: &dyn LoxCallable, arguments: Vec<Object>) -> Result<Object, LoxResult> {
+        func.call(&Interpreter::new(), arguments, None)
+    }
+
+    fn num(n: f64) -> Object {
+        Object::Num(n)
+    }
+
+    fn string(s: &str) -> Object {
+        Object::Str(s.to_string())
+    }
+
+    fn array(elements: Vec<Object>) -> Object {
+        Object::array(elements)
+    }
+
+    #[test]
+    fn div_round_modes() {
+        let div_round = |a: f64, b: f64, mode: &str| {
+            call(&NativeDivRound, vec![num(a), num(b), string(mode)]).unwrap()
+        };
+
+        assert_eq!(div_round(7.0, 2.0, "floor"), num(3.0));
+        assert_eq!(div_round(7.0, 2.0, "ceil"), num(4.0));
+        assert_eq!(div_round(7.0, 2.0, "trunc"), num(3.0));
+        assert_eq!(div_round(7.0, 2.0, "round"), num(4.0));
+
+        assert_eq!(div_round(-7.0, 2.0, "floor"), num(-4.0));
+        assert_eq!(div_round(-7.0, 2.0, "ceil"), num(-3.0));
+        assert_eq!(div_round(-7.0, 2.0, "trunc"), num(-3.0));
+        assert_eq!(div_round(-7.0, 2.0, "round"), num(-4.0));
+    }
+
+    #[test]
+    fn div_round_by_zero() {
+        assert!(call(&NativeDivRound, vec![num(1.0), num(0.0), string("floor")]).is_err());
+    }
+
+    #[test]
+    fn div_round_invalid_mode() {
+        let result = call(&NativeDivRound, vec![num(7.0), num(2.0), string("up")]);
+        assert!(matches!(result, Err(LoxResult::NativeError { .. })));
+        assert!(call(&NativeDivRound, vec![num(7.0), num(2.0), num(1.0)]).is_err());
+    }
+
+    #[test]
+    fn hash_equal_values() {
+        let hash = |o: Object| call(&NativeHash, vec![o]).unwrap();
+
+        assert_eq!(hash(num(42.0)), hash(num(42.0)));
+        assert_eq!(hash(num(0.0)), hash(num(-0.0)));
+        assert_eq!(hash(string("foo")), hash(string("foo")));
+        assert_eq!(hash(Object::Bool(true)), hash(Object::Bool(true)));
+        assert_eq!(hash(Object::Nil), hash(Object::Nil));
+
+        assert_ne!(hash(string("foo")), hash(string("bar")));
+        assert_ne!(hash(num(1.0)), hash(string("1")));
+    }
+
+    #[test]
+    fn hash_function_errors() {
+        let clock = Object::Native(Rc::new(Native::new("clock", Rc::new(NativeClock))));
+        assert!(call(&NativeHash, vec![clock]).is_err());
+    }
+
+    #[test]
+    fn special_float_predicates() {
+        let check = |func: &dyn LoxCallable, n: f64| call(func, vec![num(n)]).unwrap();
+
+        assert_eq!(check(&NativeIsNan, f64::NAN), Object::Bool(true));
+        assert_eq!(check(&NativeIsNan, 1.0), Object::Bool(false));
+        assert_eq!(check(&NativeIsInf, f64::INFINITY), Object::Bool(true));
+        assert_eq!(check(&NativeIsInf, f64::NEG_INFINITY), Object::Bool(true));
+        assert_eq!(check(&NativeIsInf, f64::NAN), Object::Bool(false));
+        assert_eq!(check(&NativeIsFinite, 1.0), Object::Bool(true));
+        assert_eq!(check(&NativeIsFinite, f64::INFINITY), Object::Bool(false));
+        assert_eq!(check(&NativeIsFinite, f64::NAN), Object::Bool(false));
+
+        assert!(call(&NativeIsNan, vec![string("NaN")]).is_err());
+        assert!(call(&NativeIsInf, vec![Object::Nil]).is_err());
+        assert!(call(&NativeIsFinite, vec![Object::Bool(true)]).is_err());
+    }
+
+    #[test]
+    fn enumerate_pairs() {
+        let result = call(&NativeEnumerate, vec![array(vec![string("a"), string("b")])]).unwrap();
+        assert_eq!(
+            result,
+            array(vec![
+                array(vec![num(0.0), string("a")]),
+                array(vec![num(1.0), string("b")]),
+            ])
+        );
+        assert_eq!(result.to_string(), "[[0, \"a\"], [1, \"b\"]]");
+    }
+
+    #[test]
+    fn enumerate_non_array_errors() {
+        assert!(call(&NativeEnumerate, vec![string("ab")]).is_err());
+    }
+
+    #[test]
+    fn partial_binds_leading_arguments() {
+        let interpreter = Interpreter::new();
+        let div_round = Object::Native(Rc::new(Native::new("div_round", Rc::new(NativeDivRound))));
+
+        let halve = NativePartial
+            .call(&interpreter, vec![div_round, array(vec![num(7.0), num(2.0)])], None)
+            .unwrap();
+        let Object::Native(halve) = halve else {
+            panic!("partial() should return a callable");
+        };
+
+        assert_eq!(halve.func.arity(), 1);
+        assert_eq!(halve.func.call(&interpreter, vec![string("floor")], None).unwrap(), num(3.0));
+        assert_eq!(halve.func.call(&interpreter, vec![string("ceil")], None).unwrap(), num(4.0));
+    }
+
+    #[test]
+    fn partial_keeps_optional_arguments_optional() {
+        let interpreter = Interpreter::new();
+        let assert = Object::Native(Rc::new(Native::new("assert", Rc::new(NativeAssert))));
+
+        let check = NativePartial.call(&interpreter, vec![assert, array(vec![Object::Bool(true)])], None).unwrap();
+        let Object::Native(check) = check else {
+            panic!("partial() should return a callable");
+        };
+
+        assert_eq!((check.func.min_arity(), check.func.arity()), (0, 1));
+    }
+
+    #[test]
+    fn partial_too_many_arguments() {
+        let clock = Object::Native(Rc::new(Native::new("clock", Rc::new(NativeClock))));
+        assert!(call(&NativePartial, vec![clock, array(vec![num(1.0)])]).is_err());
+        assert!(call(&NativePartial, vec![num(1.0), array(vec![])]).is_err());
+    }
+
+    #[test]
+    fn sort_total_mixed_types() {
+        let clock = Object::Native(Rc::new(Native::new("clock", Rc::new(NativeClock))));
+        let mixed = array(vec![
+            string("b"),
+            num(2.0),
+            clock.clone(),
+            Object::Nil,
+            array(vec![num(1.0)]),
+            Object::Bool(true),
+            num(-1.0),
+            string("a"),
+            Object::Bool(false),
+        ]);
+        let expected = array(vec![
+            Object::Nil,
+            Object::Bool(false),
+            Object::Bool(true),
+            num(-1.0),
+            num(2.0),
+            string("a"),
+            string("b"),
+            array(vec![num(1.0)]),
+            clock,
+        ]);
+
+        assert_eq!(call(&NativeSortTotal, vec![mixed.clone()]).unwrap(), expected);
+        // sorting the sorted array again doesn't move anything
+        assert_eq!(call(&NativeSortTotal, vec![expected.clone()]).unwrap(), expected);
+    }
+
+    #[test]
+    fn total_cmp_orders_special_numbers() {
+        assert!(num(f64::NEG_INFINITY).total_cmp(&num(0.0)).is_lt());
+        assert!(num(f64::NAN).total_cmp(&num(f64::INFINITY)).is_gt());
+        assert!(num(f64::NAN).total_cmp(&num(f64::NAN)).is_eq());
+    }
+
+    #[test]
+    fn object_id_of_arrays() {
+        let a = array(vec![num(1.0)]);
+        let b = array(vec![num(1.0)]);
+
+        let id = |o: &Object| call(&NativeObjectId, vec![o.clone()]).unwrap();
+        assert_eq!(id(&a), id(&a));
+        assert_ne!(id(&a), id(&b));
+        assert!(call(&NativeObjectId, vec![num(1.0)]).is_err());
+    }
+
+    #[test]
+    fn trim_whitespace() {
+        let padded = string(" \t hello world \u{3000}\n");
+
+        assert_eq!(call(&NativeTrim, vec![padded.clone()]).unwrap(), string("hello world"));
+        assert_eq!(call(&NativeTrimStart, vec![padded.clone()]).unwrap(), string("hello world \u{3000}\n"));
+        assert_eq!(call(&NativeTrimEnd, vec![padded]).unwrap(), string(" \t hello world"));
+        assert_eq!(call(&NativeTrim, vec![string("   ")]).unwrap(), string(""));
+    }
+
+    #[test]
+    fn trim_needs_a_string() {
+        assert!(call(&NativeTrim, vec![num(1.0)]).is_err());
+        assert!(call(&NativeTrimStart, vec![Object::Nil]).is_err());
+        assert!(call(&NativeTrimEnd, vec![Object::Bool(true)]).is_err());
+    }
+
+    #[test]
+    fn replace_every_occurrence() {
+        let replace = |s: &str, pattern: &str, replacement: &str| {
+            call(&NativeReplace, vec![string(s), string(pattern), string(replacement)]).unwrap()
+        };
+
+        assert_eq!(replace("a-b-c", "-", "+"), string("a+b+c"));
+        assert_eq!(replace("aaaa", "aa", "b"), string("bb"));
+        assert_eq!(replace("hello", "x", "y"), string("hello"));
+    }
+
+    #[test]
+    fn replace_errors() {
+        assert!(call(&NativeReplace, vec![string("abc"), string(""), string("x")]).is_err());
+        assert!(call(&NativeReplace, vec![string("abc"), num(1.0), string("x")]).is_err());
+        assert!(call(&NativeReplace, vec![num(1.0), string("a"), string("x")]).is_err());
+    }
+
+    #[test]
+    fn starts_and_ends_with() {
+        let starts_with = |s: &str, prefix: &str| call(&NativeStartsWith, vec![string(s), string(prefix)]).unwrap();
+        let ends_with = |s: &str, suffix: &str| call(&NativeEndsWith, vec![string(s), string(suffix)]).unwrap();
+
+        assert_eq!(starts_with("rawwr", "raw"), Object::Bool(true));
+        assert_eq!(starts_with("rawwr", "wr"), Object::Bool(false));
+        assert_eq!(starts_with("rawwr", ""), Object::Bool(true));
+        assert_eq!(ends_with("rawwr", "wr"), Object::Bool(true));
+        assert_eq!(ends_with("rawwr", "raw"), Object::Bool(false));
+    }
+
+    #[test]
+    fn starts_and_ends_with_need_strings() {
+        assert!(call(&NativeStartsWith, vec![string("1"), num(1.0)]).is_err());
+        assert!(call(&NativeEndsWith, vec![Object::Nil, string("")]).is_err());
+    }
+
+    #[test]
+    fn floor_mod_takes_sign_of_divisor() {
+        let floor_mod = |a: f64, b: f64| call(&NativeFloorMod, vec![num(a), num(b)]).unwrap();
+
+        assert_eq!(floor_mod(-1.0, 3.0), num(2.0));
+        // truncated remainder, what `%` does
+        assert_eq!(-1.0 % 3.0, -1.0);
+
+        assert_eq!(floor_mod(7.0, 3.0), num(1.0));
+        assert_eq!(floor_mod(7.0, -3.0), num(-2.0));
+        assert_eq!(floor_mod(-7.0, -3.0), num(-1.0));
+        assert_eq!(floor_mod(6.0, 3.0), num(0.0));
+    }
+
+    #[test]
+    fn floor_mod_errors() {
+        assert!(call(&NativeFloorMod, vec![num(1.0), num(0.0)]).is_err());
+        assert!(call(&NativeFloorMod, vec![num(1.0), string("3")]).is_err());
+    }
+
+    #[test]
+    fn reverse_array() {
+        let numbers = array(vec![num(1.0), num(2.0), num(3.0)]);
+        let reversed = call(&NativeReverse, vec![numbers.clone()]).unwrap();
+
+        assert_eq!(reversed, array(vec![num(3.0), num(2.0), num(1.0)]));
+        // the original is left alone
+        assert_eq!(numbers, array(vec![num(1.0), num(2.0), num(3.0)]));
+    }
+
+    #[test]
+    fn reverse_string() {
+        assert_eq!(call(&NativeReverse, vec![string("rawwr!")]).unwrap(), string("!rwwar"));
+        assert_eq!(call(&NativeReverse, vec![string("")]).unwrap(), string(""));
+        assert!(call(&NativeReverse, vec![num(12.0)]).is_err());
+    }
+
+    #[test]
+    fn entries_round_trip() {
+        let map = Object::map(HashMap::from([
+            ("b".to_string(), num(2.0)),
+            ("a".to_string(), string("one")),
+        ]));
+
+        let entries = call(&NativeEntries, vec![map.clone()]).unwrap();
+        assert_eq!(
+            entries,
+            array(vec![
+                array(vec![string("a"), string("one")]),
+                array(vec![string("b"), num(2.0)]),
+            ])
+        );
+        assert_eq!(call(&NativeMapFromPairs, vec![entries]).unwrap(), map);
+    }
+
+    #[test]
+    fn map_from_pairs_errors() {
+        let from_pairs = |pairs: Vec<Object>| call(&NativeMapFromPairs, vec![array(pairs)]);
+
+        assert!(from_pairs(vec![array(vec![string("a")])]).is_err());
+        assert!(from_pairs(vec![array(vec![string("a"), num(1.0), num(2.0)])]).is_err());
+        assert!(from_pairs(vec![array(vec![num(1.0), num(2.0)])]).is_err());
+        assert!(from_pairs(vec![string("a")]).is_err());
+        assert!(call(&NativeEntries, vec![array(vec![])]).is_err());
+    }
+
+    #[test]
+    fn unique_keeps_first_occurrence() {
+        let numbers = array([3.0, 1.0, 3.0, 2.0, 1.0, 3.0].into_iter().map(num).collect());
+        assert_eq!(
+            call(&NativeUnique, vec![numbers]).unwrap(),
+            array(vec![num(3.0), num(1.0), num(2.0)])
+        );
+
+        let words = array(["b", "a", "b", "c", "a"].into_iter().map(string).collect());
+        assert_eq!(
+            call(&NativeUnique, vec![words]).unwrap(),
+            array(vec![string("b"), string("a"), string("c")])
+        );
+    }
+
+    #[test]
+    fn unique_compares_unhashable_values() {
+        let pair = || array(vec![num(1.0), num(2.0)]);
+        let mixed = array(vec![pair(), num(1.0), pair(), string("1")]);
+        assert_eq!(
+            call(&NativeUnique, vec![mixed]).unwrap(),
+            array(vec![pair(), num(1.0), string("1")])
+        );
+        assert!(call(&NativeUnique, vec![string("aab")]).is_err());
+    }
+
+    #[test]
+    fn eq_ignore_case() {
+        let eq = |a: &str, b: &str| call(&NativeEqualsIgnoreCase, vec![string(a), string(b)]).unwrap();
+
+        assert_eq!(eq("Hello", "hello"), Object::Bool(true));
+        assert_eq!(eq("ÄPFEL", "äpfel"), Object::Bool(true));
+        assert_eq!(eq("Hello", "help"), Object::Bool(false));
+        // lowercasing doesn't turn "SS" into "ß"
+        assert_eq!(eq("straße", "STRASSE"), Object::Bool(false));
+    }
+
+    #[test]
+    fn eq_ignore_case_needs_strings() {
+        assert!(call(&NativeEqualsIgnoreCase, vec![string("1"), num(1.0)]).is_err());
+    }
+
+    #[test]
+    fn flatten_one_level() {
+        let nested = array(vec![array(vec![num(1.0), num(2.0)]), array(vec![num(3.0)])]);
+        assert_eq!(call(&NativeFlatten, vec![nested]).unwrap(), array(vec![num(1.0), num(2.0), num(3.0)]));
+
+        let deeper = array(vec![array(vec![array(vec![num(1.0)])]), num(2.0)]);
+        assert_eq!(
+            call(&NativeFlatten, vec![deeper]).unwrap(),
+            array(vec![array(vec![num(1.0)]), num(2.0)])
+        );
+        assert!(call(&NativeFlatten, vec![num(1.0)]).is_err());
+    }
+
+    #[test]
+    fn flatten_to_depth() {
+        // [[[1, [2]]], 3]
+        let nested = || {
+            array(vec![
+                array(vec![array(vec![num(1.0), array(vec![num(2.0)])])]),
+                num(3.0),
+            ])
+        };
+        let flatten = |depth: f64| call(&NativeFlatten, vec![nested(), num(depth)]).unwrap();
+
+        assert_eq!(flatten(0.0), nested());
+        assert_eq!(
+            flatten(2.0),
+            array(vec![num(1.0), array(vec![num(2.0)]), num(3.0)])
+        );
+        assert_eq!(flatten(10.0), array(vec![num(1.0), num(2.0), num(3.0)]));
+        assert!(call(&NativeFlatten, vec![nested(), num(-1.0)]).is_err());
+        assert!(call(&NativeFlatten, vec![nested(), num(1.5)]).is_err());
+    }
+
+    #[test]
+    fn flatten_keeps_an_array_that_contains_itself() {
+        let cyclic = array(vec![num(1.0), array(vec![num(2.0)])]);
+        let Object::Array(elements) = &cyclic else { unreachable!() };
+        elements.borrow_mut().push(cyclic.clone());
+
+        let flat = call(&NativeFlatten, vec![cyclic.clone(), num(1e9)]).unwrap();
+        assert_eq!(flat, array(vec![num(1.0), num(2.0), cyclic.clone()]));
+        elements.borrow_mut().clear();
+    }
+
+    #[test]
+    fn repeat_string() {
+        let repeat = |s: &str, count: f64| call(&NativeRepeatString, vec![string(s), num(count)]);
+
+        assert_eq!(repeat("ab", 3.0).unwrap(), string("ababab"));
+        assert_eq!(repeat("ab", 0.0).unwrap(), string(""));
+        assert!(repeat("ab", -1.0).is_err());
+        assert!(repeat("ab", 1.5).is_err());
+        assert!(repeat("ab", 1e18).is_err());
+        assert!(repeat("ab", 1e300).is_err());
+        assert_eq!(repeat("", 1e18).unwrap(), string(""));
+        assert!(call(&NativeRepeatString, vec![num(1.0), num(2.0)]).is_err());
+    }
+
+    #[test]
+    fn count_substrings() {
+        let count = |s: &str, needle: &str| call(&NativeCount, vec![string(s), string(needle)]);
+
+        assert_eq!(count("banana", "an").unwrap(), num(2.0));
+        // occurrences don't overlap
+        assert_eq!(count("aaaa", "aa").unwrap(), num(2.0));
+        assert_eq!(count("banana", "x").unwrap(), num(0.0));
+        assert!(count("banana", "").is_err());
+        assert!(call(&NativeCount, vec![string("1"), num(1.0)]).is_err());
+    }
+
+    #[test]
+    fn count_array_elements() {
+        let numbers = array(vec![num(1.0), num(2.0), num(1.0), string("1")]);
+
+        assert_eq!(call(&NativeCount, vec![numbers.clone(), num(1.0)]).unwrap(), num(2.0));
+        assert_eq!(call(&NativeCount, vec![numbers, num(3.0)]).unwrap(), num(0.0));
+        assert!(call(&NativeCount, vec![num(1.0), num(1.0)]).is_err());
+    }
+
+    fn date_parts(millis: f64) -> String {
+        let Object::Map(parts) = call(&NativeDateParts, vec![num(millis)]).unwrap() else {
+            panic!("date_parts() should return a map");
+        };
+        let parts = parts.borrow();
+        let part = |name: &str| match parts[name] {
+            Object::Num(n) => n,
+            _ => panic!("{name} should be a number"),
+        };
+        format!(
+            "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}",
+            part("year"), part("month"), part("day"), part("hour"), part("minute"), part("second")
+        )
+    }
+
+    #[test]
+    fn date_parts_of_known_timestamps() {
+        assert_eq!(date_parts(0.0), "1970-01-01T00:00:00");
+        assert_eq!(date_parts(951_782_400_000.0), "2000-02-29T00:00:00");
+        assert_eq!(date_parts(1_700_000_000_999.0), "2023-11-14T22:13:20");
+        assert_eq!(date_parts(-1.0), "1969-12-31T23:59:59");
+    }
+
+    #[test]
+    fn date_parts_needs_a_number() {
+        assert!(call(&NativeDateParts, vec![string("0")]).is_err());
+        assert!(call(&NativeDateParts, vec![num(f64::NAN)]).is_err());
+    }
+
+    #[test]
+    fn date_parts_rejects_timestamps_out_of_range() {
+        assert_eq!(date_parts(8.64e15), "275760-09-13T00:00:00");
+        assert_eq!(date_parts(-8.64e15), "-271821-04-20T00:00:00");
+        assert!(call(&NativeDateParts, vec![num(8.64e15 + 1.0)]).is_err());
+        assert!(call(&NativeDateParts, vec![num(1e300)]).is_err());
+        assert!(call(&NativeDateParts, vec![num(-1e300)]).is_err());
+    }
+
+    #[test]
+    fn to_fixed() {
+        let to_fixed = |n: f64, digits: f64| call(&NativeToFixed, vec![num(n), num(digits)]);
+
+        assert_eq!(to_fixed(std::f64::consts::PI, 2.0).unwrap(), string("3.14"));
+        assert_eq!(to_fixed(2.0, 3.0).unwrap(), string("2.000"));
+        assert_eq!(to_fixed(0.999, 2.0).unwrap(), string("1.00"));
+        assert_eq!(to_fixed(-1.26, 1.0).unwrap(), string("-1.3"));
+    }
+
+    #[test]
+    fn to_fixed_errors() {
+        assert!(call(&NativeToFixed, vec![num(1.0), num(-1.0)]).is_err());
+        assert!(call(&NativeToFixed, vec![num(1.0), num(0.5)]).is_err());
+        assert!(call(&NativeToFixed, vec![num(1.0), num(101.0)]).is_err());
+        assert!(call(&NativeToFixed, vec![num(1.0), num(1e12)]).is_err());
+        assert!(call(&NativeToFixed, vec![num(1.0), num(100.0)]).is_ok());
+        assert!(call(&NativeToFixed, vec![string("1"), num(1.0)]).is_err());
+    }
+
+    #[test]
+    fn swap_in_place() {
+        let letters = array(vec![string("a"), string("b"), string("c")]);
+        let swapped = call(&NativeSwap, vec![letters.clone(), num(0.0), num(2.0)]).unwrap();
+
+        assert_eq!(letters, array(vec![string("c"), string("b"), string("a")]));
+        assert_eq!(swapped, letters);
+        call(&NativeSwap, vec![letters.clone(), num(1.0), num(1.0)]).unwrap();
+        assert_eq!(letters, array(vec![string("c"), string("b"), string("a")]));
+    }
+
+    #[test]
+    fn swap_errors() {
+        let letters = array(vec![string("a"), string("b")]);
+
+        assert!(call(&NativeSwap, vec![letters.clone(), num(0.0), num(2.0)]).is_err());
+        assert!(call(&NativeSwap, vec![letters.clone(), num(-1.0), num(0.0)]).is_err());
+        assert!(call(&NativeSwap, vec![letters.clone(), num(0.5), num(1.0)]).is_err());
+        assert!(call(&NativeSwap, vec![string("ab"), num(0.0), num(1.0)]).is_err());
+    }
+
+    #[test]
+    fn map_get_or_default() {
+        let map = Object::map(HashMap::from([("a".to_string(), num(1.0))]));
+
+        assert_eq!(call(&NativeMapGet, vec![map.clone(), string("a"), num(0.0)]).unwrap(), num(1.0));
+        assert_eq!(call(&NativeMapGet, vec![map.clone(), string("b"), num(0.0)]).unwrap(), num(0.0));
+        assert!(call(&NativeMapGet, vec![map, num(1.0), num(0.0)]).is_err());
+        assert!(call(&NativeMapGet, vec![array(vec![]), string("a"), num(0.0)]).is_err());
+    }
+
+    #[test]
+    fn convert_between_types() {
+        let convert = |value: Object, target: &str| call(&NativeConvert, vec![value, string(target)]);
+
+        assert_eq!(convert(string(" 4.5"), "number").unwrap(), num(4.5));
+        assert_eq!(convert(num(2.0), "string").unwrap(), string("2"));
+        assert_eq!(convert(num(0.0), "boolean").unwrap(), Object::Bool(true));
+        assert_eq!(convert(Object::Nil, "boolean").unwrap(), Object::Bool(false));
+        assert_eq!(convert(string("false"), "boolean").unwrap(), Object::Bool(false));
+        assert_eq!(convert(string("true"), "boolean").unwrap(), Object::Bool(true));
+        assert_eq!(convert(num(3.0), "number").unwrap(), num(3.0));
+        assert_eq!(convert(string("abc"), "string").unwrap(), string("abc"));
+    }
+
+    #[test]
+    fn convert_rejects_unsupported_conversions() {
+        let convert = |value: Object, target: &str| call(&NativeConvert, vec![value, string(target)]);
+
+        assert!(convert(string("abc"), "number").is_err());
+        assert!(convert(string("yes"), "boolean").is_err());
+        assert!(convert(Object::Bool(true), "number").is_err());
+        assert!(convert(Object::Nil, "string").is_err());
+        assert!(convert(num(1.0), "array").is_err());
+        assert!(call(&NativeConvert, vec![num(1.0), num(2.0)]).is_err());
+    }
+
+    #[test]
+    fn clamp_index_in_range() {
+        assert_eq!(call(&NativeClampIndex, vec![num(0.0), num(5.0)]).unwrap(), num(0.0));
+        assert_eq!(call(&NativeClampIndex, vec![num(3.0), num(5.0)]).unwrap(), num(3.0));
+    }
+
+    #[test]
+    fn clamp_index_over_range() {
+        assert_eq!(call(&NativeClampIndex, vec![num(5.0), num(5.0)]).unwrap(), num(4.0));
+        assert_eq!(call(&NativeClampIndex, vec![num(100.0), num(5.0)]).unwrap(), num(4.0));
+    }
+
+    #[test]
+    fn clamp_index_wraps_negative() {
+        assert_eq!(call(&NativeClampIndex, vec![num(-1.0), num(5.0)]).unwrap(), num(4.0));
+        assert_eq!(call(&NativeClampIndex, vec![num(-5.0), num(5.0)]).unwrap(), num(0.0));
+        assert_eq!(call(&NativeClampIndex, vec![num(-8.0), num(5.0)]).unwrap(), num(0.0));
+    }
+
+    #[test]
+    fn clamp_index_rejects_non_integers() {
+        assert!(call(&NativeClampIndex, vec![num(1.5), num(5.0)]).is_err());
+        assert!(call(&NativeClampIndex, vec![num(1.0), string("5")]).is_err());
+        assert!(call(&NativeClampIndex, vec![num(1.0), num(0.0)]).is_err());
+    }
+
+    #[test]
+    fn pad_start_to_width() {
+        assert_eq!(call(&NativePadStart, vec![string("42"), num(5.0), string("0")]).unwrap(), string("00042"));
+        assert_eq!(call(&NativePadStart, vec![string("ü"), num(4.0), string("ab")]).unwrap(), string("abaü"));
+    }
+
+    #[test]
+    fn pad_end_to_width() {
+        assert_eq!(call(&NativePadEnd, vec![string("ab"), num(5.0), string(".")]).unwrap(), string("ab..."));
+        assert_eq!(call(&NativePadEnd, vec![string("é"), num(4.0), string("xy")]).unwrap(), string("éxyx"));
+    }
+
+    #[test]
+    fn pad_is_noop_when_long_enough() {
+        assert_eq!(call(&NativePadStart, vec![string("hello"), num(3.0), string(" ")]).unwrap(), string("hello"));
+        assert_eq!(call(&NativePadEnd, vec![string("hello"), num(5.0), string(" ")]).unwrap(), string("hello"));
+    }
+
+    #[test]
+    fn pad_rejects_bad_arguments() {
+        assert!(call(&NativePadStart, vec![string("a"), num(3.0), string("")]).is_err());
+        assert!(call(&NativePadEnd, vec![num(1.0), num(3.0), string(" ")]).is_err());
+        assert!(call(&NativePadEnd, vec![string("a"), num(-1.0), string(" ")]).is_err());
+        assert!(call(&NativePadStart, vec![string("a"), num(1e20), string("x")]).is_err());
+        assert!(call(&NativePadEnd, vec![string("a"), num(f64::INFINITY), string("x")]).is_err());
+    }
+
+    fn string_length() -> Object {
+        Object::Native(Rc::new(Native::new("len", Rc::new(NativeLen))))
+    }
+
+    #[test]
+    fn max_by_and_min_by_length() {
+        let words = array(vec![string("fig"), string("banana"), string("kiwi"), string("cherry"), string("pea")]);
+        assert_eq!(call(&NativeMaxBy, vec![words.clone(), string_length()]).unwrap(), string("banana"));
+        assert_eq!(call(&NativeMinBy, vec![words, string_length()]).unwrap(), string("fig"));
+    }
+
+    #[test]
+    fn max_by_errors() {
+        assert!(call(&NativeMaxBy, vec![array(vec![]), string_length()]).is_err());
+        assert!(call(&NativeMinBy, vec![array(vec![]), string_length()]).is_err());
+        // len() fails on the number
+        assert!(call(&NativeMaxBy, vec![array(vec![string("a"), num(1.0)]), string_length()]).is_err());
+        // strings don't compare
+        let trim = Object::Native(Rc::new(Native::new("trim", Rc::new(NativeTrim))));
+        assert!(call(&NativeMinBy, vec![array(vec![string("a"), string("b")]), trim]).is_err());
+    }
+
+    #[test]
+    fn len_of_strings_arrays_and_maps() {
+        assert_eq!(call(&NativeLen, vec![string("héllo")]).unwrap(), num(5.0));
+        assert_eq!(call(&NativeLen, vec![array(vec![num(1.0), num(2.0)])]).unwrap(), num(2.0));
+        assert_eq!(call(&NativeLen, vec![Object::map(HashMap::new())]).unwrap(), num(0.0));
+        assert!(call(&NativeLen, vec![num(1.0)]).is_err());
+    }
+
+    #[test]
+    fn push_and_pop_modify_in_place() {
+        let numbers = array(vec![num(1.0)]);
+        call(&NativePush, vec![numbers.clone(), num(2.0)]).unwrap();
+        assert_eq!(numbers, array(vec![num(1.0), num(2.0)]));
+
+        assert_eq!(call(&NativePop, vec![numbers.clone()]).unwrap(), num(2.0));
+        assert_eq!(call(&NativePop, vec![numbers.clone()]).unwrap(), num(1.0));
+        assert!(call(&NativePop, vec![numbers]).is_err());
+    }
+
+    #[test]
+    fn shuffle_is_reproducible_with_a_seed() {
+        let numbers = array((0..20).map(|n| num(n as f64)).collect());
+        let shuffle = |seed| NativeShuffle.call(&seeded(seed), vec![numbers.clone()], None).unwrap();
+
+        let shuffled = shuffle(3);
+        assert_eq!(shuffled, shuffle(3));
+        assert_ne!(shuffled, numbers);
+
+        let Object::Array(shuffled) = shuffled else {
+            panic!("expected an array");
+        };
+        let mut sorted = shuffled.borrow().clone();
+        sorted.sort_by(|a, b| a.total_cmp(b));
+        assert_eq!(array(sorted), numbers);
+    }
+
+    #[test]
+    fn seed_restarts_random() {
+        let interpreter = Interpreter::new();
+        let seed_then_random = || {
+            NativeSeed.call(&interpreter, vec![num(5.0)], None).unwrap();
+            NativeRandom.call(&interpreter, vec![], None).unwrap()
+        };
+        assert_eq!(seed_then_random(), seed_then_random());
+        assert!(call(&NativeShuffle, vec![num(1.0)]).is_err());
+    }
+
+    fn seeded(seed: u64) -> Interpreter {
+        Interpreter::with_config(Config { seed: Some(seed), ..Config::default() })
+    }
+
+    #[test]
+    fn sample_fewer_than_length() {
+        let numbers = array((0..10).map(|n| num(n as f64)).collect());
+        let sample = |seed| NativeSample.call(&seeded(seed), vec![numbers.clone(), num(4.0)], None).unwrap();
+
+        let picked = sample(11);
+        assert_eq!(picked, sample(11));
+
+        let Object::Array(picked) = picked else {
+            panic!("expected an array");
+        };
+        let mut picked = picked.borrow().clone();
+        assert_eq!(picked.len(), 4);
+        picked.sort_by(|a, b| a.total_cmp(b));
+        picked.dedup();
+        assert_eq!(picked.len(), 4);
+    }
+
+    #[test]
+    fn sample_whole_array() {
+        let numbers = array((0..5).map(|n| num(n as f64)).collect());
+        let Object::Array(picked) = NativeSample.call(&seeded(1), vec![numbers.clone(), num(5.0)], None).unwrap() else {
+            panic!("expected an array");
+        };
+        let mut picked = picked.borrow().clone();
+        picked.sort_by(|a, b| a.total_cmp(b));
+        assert_eq!(array(picked), numbers);
+    }
+
+    #[test]
+    fn sample_rejects_bad_counts() {
+        let numbers = array(vec![num(1.0), num(2.0)]);
+        assert!(call(&NativeSample, vec![numbers.clone(), num(3.0)]).is_err());
+        assert!(call(&NativeSample, vec![numbers.clone(), num(-1.0)]).is_err());
+        assert!(call(&NativeSample, vec![numbers.clone(), num(1.5)]).is_err());
+        match call(&NativeSample, vec![numbers, num(1e20)]) {
+            Err(LoxResult::NativeError { message }) => {
+                assert_eq!(message, "sample() count 100000000000000000000 is larger than the array length 2.")
+            }
+            other => panic!("expected a native error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn expect_passes_non_nil_values_through() {
+        assert_eq!(call(&NativeExpect, vec![num(0.0), string("missing")]).unwrap(), num(0.0));
+        assert_eq!(call(&NativeExpect, vec![Object::Bool(false), string("missing")]).unwrap(), Object::Bool(false));
+    }
+
+    #[test]
+    fn expect_raises_the_message_on_nil() {
+        match call(&NativeExpect, vec![Object::Nil, string("user not found")]) {
+            Err(LoxResult::NativeError { message }) => assert_eq!(message, "user not found"),
+            other => panic!("expected a native error, got {:?}", other),
+        }
+        assert!(call(&NativeExpect, vec![Object::Nil, num(1.0)]).is_err());
+    }
+
+    #[test]
+    fn map_merge_prefers_the_second_map() {
+        let map = |entries: &[(&str, f64)]| {
+            Object::map(entries.iter().map(|(k, v)| (k.to_string(), num(*v))).collect())
+        };
+        let first = map(&[("a", 1.0), ("b", 2.0)]);
+
+        let merged = call(&NativeMapMerge, vec![first.clone(), map(&[("b", 3.0), ("c", 4.0)])]).unwrap();
+        assert_eq!(merged, map(&[("a", 1.0), ("b", 3.0), ("c", 4.0)]));
+        assert_eq!(first, map(&[("a", 1.0), ("b", 2.0)]));
+
+        let union = call(&NativeMapMerge, vec![map(&[("a", 1.0)]), map(&[("z", 9.0)])]).unwrap();
+        assert_eq!(union, map(&[("a", 1.0), ("z", 9.0)]));
+
+        assert!(call(&NativeMapMerge, vec![first, array(vec![])]).is_err());
+    }
+
+    #[test]
+    fn substring_counts_characters() {
+        let substring = |s: &str, start: f64, end: f64| call(&NativeSubstring, vec![string(s), num(start), num(end)]);
+
+        assert_eq!(substring("hello", 1.0, 3.0).unwrap(), string("el"));
+        assert_eq!(substring("héllo", 1.0, 2.0).unwrap(), string("é"));
+        assert_eq!(substring("hello", 2.0, 2.0).unwrap(), string(""));
+        assert_eq!(substring("hello", 3.0, 5.0).unwrap(), string("lo"));
+        assert_eq!(substring("hello", 5.0, 5.0).unwrap(), string(""));
+    }
+
+    #[test]
+    fn substring_rejects_bad_indices() {
+        let substring = |start: f64, end: f64| call(&NativeSubstring, vec![string("hello"), num(start), num(end)]);
+
+        assert!(substring(3.0, 1.0).is_err());
+        assert!(substring(3.0, 6.0).is_err());
+        assert!(substring(10.0, 12.0).is_err());
+        assert!(substring(-1.0, 2.0).is_err());
+        assert!(substring(0.5, 2.0).is_err());
+        assert!(call(&NativeSubstring, vec![num(1.0), num(0.0), num(1.0)]).is_err());
+        assert!(call(&NativeSubstring, vec![string("hello"), string("0"), num(1.0)]).is_err());
+    }
+
+    #[test]
+    fn upper_and_lower() {
+        assert_eq!(call(&NativeUpper, vec![string("Hello, wörld")]).unwrap(), string("HELLO, WÖRLD"));
+        assert_eq!(call(&NativeLower, vec![string("Hello, WÖRLD")]).unwrap(), string("hello, wörld"));
+        assert!(call(&NativeUpper, vec![num(1.0)]).is_err());
+        assert!(call(&NativeLower, vec![Object::Nil]).is_err());
+    }
+
+    #[test]
+    fn str_to_num_parses_numbers() {
+        assert_eq!(call(&NativeStrToNum, vec![string("2.5")]).unwrap(), num(2.5));
+        assert_eq!(call(&NativeStrToNum, vec![string("-2")]).unwrap(), num(-2.0));
+        assert_eq!(call(&NativeStrToNum, vec![string("abc")]).unwrap(), Object::Nil);
+        assert_eq!(call(&NativeStrToNum, vec![string("")]).unwrap(), Object::Nil);
+        for not_a_literal in ["inf", "infinity", "-inf", "NaN", " 3", "3 ", "1e5", "+1", ".5", "5.", "1.2.3"] {
+            assert_eq!(call(&NativeStrToNum, vec![string(not_a_literal)]).unwrap(), Object::Nil, "{not_a_literal}");
+        }
+        // a literal too big for a double doesn't become infinity
+        assert_eq!(call(&NativeStrToNum, vec![string(&"9".repeat(400))]).unwrap(), Object::Nil);
+        assert!(call(&NativeStrToNum, vec![num(3.0)]).is_err());
+    }
+
+    #[test]
+    fn char_at_indexes_characters() {
+        let char_at = |s: &str, index: f64| call(&NativeCharAt, vec![string(s), num(index)]);
+
+        assert_eq!(char_at("hello", 0.0).unwrap(), string("h"));
+        assert_eq!(char_at("hello", 4.0).unwrap(), string("o"));
+        // 'é' is two bytes and '€' three, byte indices would land inside them
+        assert_eq!(char_at("héllo", 1.0).unwrap(), string("é"));
+        assert_eq!(char_at("héllo", 2.0).unwrap(), string("l"));
+        assert_eq!(char_at("a€b", 2.0).unwrap(), string("b"));
+    }
+
+    #[test]
+    fn char_at_rejects_bad_indices() {
+        let char_at = |s: &str, index: f64| call(&NativeCharAt, vec![string(s), num(index)]);
+
+        assert!(char_at("héllo", 5.0).is_err());
+        assert!(char_at("", 0.0).is_err());
+        assert!(char_at("hello", -1.0).is_err());
+        assert!(char_at("hello", 1.5).is_err());
+        assert!(call(&NativeCharAt, vec![num(1.0), num(0.0)]).is_err());
     }
 }
@@ -1,4 +1,9 @@
 use core::fmt;
+use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
 use std::rc::Rc;
 
 //use crate::callable::Callable;
@@ -7,7 +12,7 @@ use crate::class::*;
 use crate::function::*;
 use crate::native_functions::*;
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone)]
 pub enum Object {
     Num(f64),
     Str(String),
@@ -16,11 +21,214 @@ pub enum Object {
     Class(Rc<Class>),
     Instance(Rc<Instance>),
     Native(Rc<Native>),
+    Array(Rc<RefCell<Vec<Object>>>),
+    Map(Rc<RefCell<HashMap<String, Object>>>),
+    // Filled by `sb_append`, so building a string in a loop doesn't copy it on every step.
+    StringBuilder(Rc<RefCell<String>>),
     Nil,
     ArithmeticError,
     DivByZeroError,
 }
 
+impl Object {
+    pub fn array(elements: Vec<Object>) -> Object {
+        Object::Array(Rc::new(RefCell::new(elements)))
+    }
+
+    pub fn map(entries: HashMap<String, Object>) -> Object {
+        Object::Map(Rc::new(RefCell::new(entries)))
+    }
+
+    pub fn string_builder() -> Object {
+        Object::StringBuilder(Rc::new(RefCell::new(String::new())))
+    }
+
+    // A total order over every kind of value, used to sort arrays that mix types. It is only meant
+    // to make such sorts deterministic and is not what `<` and `>` mean: values are ordered by
+    // type first (nil, booleans, numbers, strings, arrays, maps, then everything else) and then by
+    // value. Functions, classes and instances have no meaningful order among themselves, so they
+    // compare equal and a stable sort keeps them where they were.
+    pub fn total_cmp(&self, other: &Object) -> Ordering {
+        self.total_cmp_within(other, &mut Vec::new())
+    }
+
+    // `visiting` holds the pairs of arrays compared further up, see `eq_within`.
+    fn total_cmp_within(&self, other: &Object, visiting: &mut Vec<(*const (), *const ())>) -> Ordering {
+        match (self, other) {
+            (Object::Bool(a), Object::Bool(b)) => a.cmp(b),
+            (Object::Num(a), Object::Num(b)) => a.total_cmp(b),
+            (Object::Str(a), Object::Str(b)) => a.cmp(b),
+            (Object::Array(a), Object::Array(b)) => {
+                let pair = (Rc::as_ptr(a) as *const (), Rc::as_ptr(b) as *const ());
+                if Rc::ptr_eq(a, b) || visiting.contains(&pair) {
+                    return Ordering::Equal;
+                }
+                visiting.push(pair);
+                let (a, b) = (a.borrow(), b.borrow());
+                let ordering = a
+                    .iter()
+                    .zip(b.iter())
+                    .map(|(x, y)| x.total_cmp_within(y, visiting))
+                    .find(|o| o.is_ne())
+                    .unwrap_or_else(|| a.len().cmp(&b.len()));
+                visiting.pop();
+                ordering
+            }
+            _ => self.type_rank().cmp(&other.type_rank()),
+        }
+    }
+
+    // Arrays and maps can contain themselves, e.g. after `push(a, a)`. `visiting` holds the pairs
+    // of containers compared further up. Meeting one of them again means both sides cycle the
+    // same way so far, so that pair is taken as equal and the rest of the comparison decides.
+    fn eq_within(&self, other: &Object, visiting: &mut Vec<(*const (), *const ())>) -> bool {
+        match (self, other) {
+            (Object::Num(a), Object::Num(b)) => a == b,
+            (Object::Str(a), Object::Str(b)) => a == b,
+            (Object::Bool(a), Object::Bool(b)) => a == b,
+            (Object::Func(a), Object::Func(b)) => a == b,
+            (Object::Class(a), Object::Class(b)) => a == b,
+            (Object::Instance(a), Object::Instance(b)) => Rc::ptr_eq(a, b),
+            (Object::Native(a), Object::Native(b)) => a == b,
+            (Object::StringBuilder(a), Object::StringBuilder(b)) => a == b,
+            (Object::Array(a), Object::Array(b)) => {
+                let pair = (Rc::as_ptr(a) as *const (), Rc::as_ptr(b) as *const ());
+                if Rc::ptr_eq(a, b) || visiting.contains(&pair) {
+                    return true;
+                }
+                visiting.push(pair);
+                let (a, b) = (a.borrow(), b.borrow());
+                let equal = a.len() == b.len() && a.iter().zip(b.iter()).all(|(x, y)| x.eq_within(y, visiting));
+                visiting.pop();
+                equal
+            }
+            (Object::Map(a), Object::Map(b)) => {
+                let pair = (Rc::as_ptr(a) as *const (), Rc::as_ptr(b) as *const ());
+                if Rc::ptr_eq(a, b) || visiting.contains(&pair) {
+                    return true;
+                }
+                visiting.push(pair);
+                let (a, b) = (a.borrow(), b.borrow());
+                let equal = a.len() == b.len()
+                    && a.iter().all(|(key, x)| b.get(key).is_some_and(|y| x.eq_within(y, visiting)));
+                visiting.pop();
+                equal
+            }
+            (Object::Nil, Object::Nil)
+            | (Object::ArithmeticError, Object::ArithmeticError)
+            | (Object::DivByZeroError, Object::DivByZeroError) => true,
+            _ => false,
+        }
+    }
+
+    // Whether `+` turns this into text when the other operand is a string. Nil, functions and
+    // classes are left out since adding them to a string is almost always a mistake.
+    fn concatenates(&self) -> bool {
+        matches!(
+            self,
+            Object::Num(_) | Object::Bool(_) | Object::Array(_) | Object::Map(_) | Object::Instance(_)
+        )
+    }
+
+    fn type_rank(&self) -> u8 {
+        match self {
+            Object::Nil => 0,
+            Object::Bool(_) => 1,
+            Object::Num(_) => 2,
+            Object::Str(_) => 3,
+            Object::Array(_) => 4,
+            Object::Map(_) => 5,
+            Object::Func(_) | Object::Native(_) => 6,
+            Object::Class(_) => 7,
+            Object::Instance(_) => 8,
+            Object::StringBuilder(_) => 9,
+            Object::ArithmeticError | Object::DivByZeroError => 10,
+        }
+    }
+
+    // `DefaultHasher::new()` always starts from the same keys, so unlike a `RandomState` hasher
+    // the result is the same on every run. Only value types can be hashed.
+    pub fn stable_hash(&self) -> Option<u64> {
+        let mut hasher = DefaultHasher::new();
+        match self {
+            Object::Num(x) => {
+                // -0.0 == 0.0, so they have to hash the same
+                let x = if *x == 0.0 { 0.0 } else { *x };
+                0_u8.hash(&mut hasher);
+                x.to_bits().hash(&mut hasher);
+            }
+            Object::Str(x) => {
+                1_u8.hash(&mut hasher);
+                x.hash(&mut hasher);
+            }
+            Object::Bool(x) => {
+                2_u8.hash(&mut hasher);
+                x.hash(&mut hasher);
+            }
+            Object::Nil => 3_u8.hash(&mut hasher),
+            _ => return None,
+        }
+        Some(hasher.finish())
+    }
+}
+
+impl PartialEq for Object {
+    fn eq(&self, other: &Object) -> bool {
+        self.eq_within(other, &mut Vec::new())
+    }
+}
+
+impl Object {
+    // How a value is shown inside an array or map, where strings keep their quotes.
+    fn quoted(&self, visiting: &mut Vec<*const ()>) -> String {
+        match self {
+            Object::Str(s) => format!("\"{s}\""),
+            _ => self.container_to_string(visiting),
+        }
+    }
+
+    // `visiting` holds the containers printed further up, one that contains itself is shown as
+    // `[...]` or `{...}` the second time.
+    fn container_to_string(&self, visiting: &mut Vec<*const ()>) -> String {
+        match self {
+            Object::Array(a) => {
+                let id = Rc::as_ptr(a) as *const ();
+                if visiting.contains(&id) {
+                    return "[...]".to_string();
+                }
+                visiting.push(id);
+                let elements = a
+                    .borrow()
+                    .iter()
+                    .map(|element| element.quoted(visiting))
+                    .collect::<Vec<String>>()
+                    .join(", ");
+                visiting.pop();
+                format!("[{}]", elements)
+            }
+            Object::Map(m) => {
+                let id = Rc::as_ptr(m) as *const ();
+                if visiting.contains(&id) {
+                    return "{...}".to_string();
+                }
+                visiting.push(id);
+                // sorted by key, a `HashMap` has no stable order of its own
+                let m = m.borrow();
+                let mut keys = m.keys().collect::<Vec<&String>>();
+                keys.sort();
+                let entries = keys
+                    .into_iter()
+                    .map(|k| format!("\"{k}\": {}", m[k].quoted(visiting)))
+                    .collect::<Vec<String>>()
+                    .join(", ");
+                visiting.pop();
+                format!("{{{}}}", entries)
+            }
+            _ => self.to_string(),
+        }
+    }
+}
+
 impl fmt::Display for Object {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
@@ -29,8 +237,10 @@ impl fmt::Display for Object {
             Object::Bool(x) => write!(f, "{}", x),
             Object::Func(_) => write!(f, "<func>"),
             Object::Class(c) => write!(f, "<Class {}>", c),
-            Object::Instance(i) => write!(f, "<Instance {}>", i),
-            Object::Native(n) => write!(f, "<Native {}>", n),
+            Object::Instance(i) => write!(f, "{}", i),
+            Object::Native(n) => write!(f, "{}", n),
+            Object::Array(_) | Object::Map(_) => write!(f, "{}", self.container_to_string(&mut Vec::new())),
+            Object::StringBuilder(_) => write!(f, "<string builder>"),
             Object::Nil => write!(f, "nil"),
             Object::ArithmeticError => write!(f, "ArithmeticError"),
             Object::DivByZeroError => write!(f, "DivByZeroError"),
@@ -66,6 +276,25 @@ impl std::ops::Div for Object {
     }
 }
 
+// Like Rust's `%` the result has the sign of the dividend, `floor_mod()` is the one that takes the
+// sign of the divisor.
+impl std::ops::Rem for Object {
+    type Output = Object;
+
+    fn rem(self, other: Self) -> Object {
+        match (self, other) {
+            (Object::Num(left), Object::Num(right)) => {
+                if right == 0 as f64 {
+                    Object::DivByZeroError
+                } else {
+                    Object::Num(left % right)
+                }
+            }
+            _ => Object::ArithmeticError,
+        }
+    }
+}
+
 impl std::ops::Sub for Object {
     type Output = Object;
 
@@ -84,6 +313,9 @@ impl std::ops::Add for Object {
         match (self, other) {
             (Object::Num(left), Object::Num(right)) => Object::Num(left + right),
             (Object::Str(left), Object::Str(right)) => Object::Str(format!("{}{}", left, right)),
+            // a string and a value that prints sensibly are concatenated
+            (Object::Str(left), right) if right.concatenates() => Object::Str(format!("{}{}", left, right)),
+            (left, Object::Str(right)) if left.concatenates() => Object::Str(format!("{}{}", left, right)),
             _ => Object::ArithmeticError,
         }
     }
@@ -4,6 +4,7 @@ use crate::object::*;
 use crate::stmt::*;
 use crate::token::*;
 
+use std::collections::HashMap;
 use std::rc::Rc;
 
 use crate::token::TokenType::*;
@@ -14,31 +15,127 @@ macro_rules! match_token {
     }
 }
 
+enum Associativity {
+    Left,
+    Right,
+}
+
+// Binding power and associativity of every binary operator, higher binds tighter. Unary operators,
+// calls and property access bind tighter than all of these.
+fn binary_operator(token_type: &TokenType) -> Option<(u8, Associativity)> {
+    match token_type {
+        Or => Some((1, Associativity::Left)),
+        And => Some((2, Associativity::Left)),
+        BangEqual | EqualEqual => Some((3, Associativity::Left)),
+        Greater | GreaterEqual | Less | LessEqual => Some((4, Associativity::Left)),
+        Minus | Plus => Some((5, Associativity::Left)),
+        Star | Slash | Percent => Some((6, Associativity::Left)),
+        _ => None,
+    }
+}
+
+// The first and last token of a node, as indices into the tokens the parser was given. The tokens
+// carry the position in the source.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+// Where the nodes of a parse came from, see `Parser::parse_with_spans`. Nodes the parser desugars
+// into (like the loop behind a `for`) have no span of their own.
+#[derive(Default)]
+pub struct Spans {
+    exprs: HashMap<Expr, Span>,
+    stmts: HashMap<Stmt, Span>,
+}
+
+impl Spans {
+    pub fn expr(&self, expr: &Expr) -> Option<Span> {
+        self.exprs.get(expr).copied()
+    }
+
+    pub fn stmt(&self, stmt: &Stmt) -> Option<Span> {
+        self.stmts.get(stmt).copied()
+    }
+}
+
+fn declared_variables(declarations: &[Rc<Stmt>]) -> Vec<Token> {
+    declarations
+        .iter()
+        .filter_map(|declaration| match declaration.as_ref() {
+            Stmt::Var(var) => Some(var.name.clone()),
+            _ => None,
+        })
+        .collect()
+}
+
+// Puts the variables a loop declares into a block around it, so they go out of scope with it.
+fn enclose_loop(declarations: Vec<Rc<Stmt>>, body: Rc<Stmt>, line: i32) -> Rc<Stmt> {
+    if declarations.is_empty() {
+        return body;
+    }
+    let mut statements = declarations;
+    statements.push(body);
+    Rc::new(Stmt::Block(Rc::new(BlockStmt {
+        statements: Rc::new(statements),
+        line,
+    })))
+}
+
 pub struct Parser {
     tokens: Vec<Token>,
     current: usize,
     had_error: bool,
+    // Only collected when asked for, most callers don't need them.
+    spans: Option<Spans>,
 }
 
 impl Parser {
-    pub fn new(tokens: Vec<Token>) -> Self {
+    pub fn new(mut tokens: Vec<Token>) -> Self {
+        // Everything below relies on the token list being terminated by an EOF token. The scanner
+        // always emits one, but tokens handed in by an embedder might not.
+        if tokens.last().map(|t| t.token_type) != Some(EOF) {
+            let line = tokens.last().map_or(1, |t| t.line);
+            tokens.push(Token::new(EOF, "".to_string(), None, line, 0));
+        }
+
         Parser {
             tokens,
             current: 0,
             had_error: false,
+            spans: None,
         }
     }
 
     pub fn parse(&mut self) -> Result<Vec<Rc<Stmt>>, LoxResult> {
         let mut statements = Vec::new();
 
-        while !self.is_at_end() {
-            statements.push(self.declaration()?);
+        while let Some(statement) = self.parse_one()? {
+            statements.push(statement);
         }
 
         Ok(statements)
     }
 
+    // Like `parse`, but also records the span of every node.
+    pub fn parse_with_spans(&mut self) -> Result<(Vec<Rc<Stmt>>, Spans), LoxResult> {
+        self.spans = Some(Spans::default());
+        let statements = self.parse();
+        let spans = self.spans.take().unwrap_or_default();
+        Ok((statements?, spans))
+    }
+
+    // Parses the next top-level statement, `None` once all tokens are used up. After an error the
+    // parser has already skipped ahead to the start of the next statement, so a host can report
+    // the error and keep calling this.
+    pub fn parse_one(&mut self) -> Result<Option<Rc<Stmt>>, LoxResult> {
+        if self.is_at_end() {
+            return Ok(None);
+        }
+        self.declaration().map(Some)
+    }
+
     fn is_match(&mut self, token_types: &[TokenType]) -> bool {
         for t in token_types {
             if self.check(t) {
@@ -60,6 +157,13 @@ impl Parser {
         self.tokens.get(self.current).unwrap().clone()
     }
 
+    fn check_next(&self, token_type: &TokenType) -> bool {
+        match self.tokens.get(self.current + 1) {
+            Some(token) => &token.token_type == token_type,
+            None => false,
+        }
+    }
+
     fn is_at_end(&self) -> bool {
         self.peek().token_type == EOF
     }
@@ -85,7 +189,7 @@ impl Parser {
 
             if matches!(
                 self.peek().token_type,
-                Class | Fun | Var | For | If | While | Print | Return
+                Class | Fun | Var | For | If | While | Print | Return | Break | Continue
             ) {
                 return;
             }
@@ -110,6 +214,27 @@ impl Parser {
         !self.had_error
     }
 
+    pub fn tokens(&self) -> &[Token] {
+        &self.tokens
+    }
+
+    // Records that `expr` covers everything from the token at `start` up to the last consumed one.
+    fn spanned_expr(&mut self, start: usize, expr: Expr) -> Expr {
+        if let Some(spans) = &mut self.spans {
+            let span = Span { start, end: self.current.saturating_sub(1) };
+            spans.exprs.insert(expr.clone(), span);
+        }
+        expr
+    }
+
+    fn spanned_stmt(&mut self, start: usize, stmt: Rc<Stmt>) -> Rc<Stmt> {
+        if let Some(spans) = &mut self.spans {
+            let span = Span { start, end: self.current.saturating_sub(1) };
+            spans.stmts.insert((*stmt).clone(), span);
+        }
+        stmt
+    }
+
     // ------------------------------------------------------------------------
 
     fn expression(&mut self) -> Result<Expr, LoxResult> {
@@ -117,6 +242,7 @@ impl Parser {
     }
 
     fn declaration(&mut self) -> Result<Rc<Stmt>, LoxResult> {
+        let start = self.current;
         let result = if match_token!(self, Class) {
             self.class_declaration()
         } else if match_token!(self, Fun) {
@@ -131,7 +257,7 @@ impl Parser {
             self.synchronize();
         }
 
-        result
+        result.map(|stmt| self.spanned_stmt(start, stmt))
     }
 
     fn class_declaration(&mut self) -> Result<Rc<Stmt>, LoxResult> {
@@ -148,10 +274,18 @@ impl Parser {
 
         self.consume(&LeftBrace, "Expect '{{' before class body.")?;
 
+        let mut fields = Vec::new();
         let mut methods = Vec::new();
+        let mut static_methods = Vec::new();
 
         while !self.check(&RightBrace) && !self.is_at_end() {
-            methods.push(self.function("method")?);
+            if self.check(&Identifier) && self.check_next(&Equal) {
+                fields.push(self.field_declaration()?);
+            } else if match_token!(self, Class) {
+                static_methods.push(self.function("static method")?);
+            } else {
+                methods.push(self.function("method")?);
+            }
         }
 
         self.consume(&RightBrace, "Expect '}}' after class body.")?;
@@ -159,11 +293,27 @@ impl Parser {
         Ok(Rc::new(Stmt::Class(Rc::new(ClassStmt {
             name,
             superclass,
+            fields: Rc::new(fields),
             methods: Rc::new(methods),
+            static_methods: Rc::new(static_methods),
         }))))
     }
 
+    fn field_declaration(&mut self) -> Result<Rc<Stmt>, LoxResult> {
+        let name = self.consume(&Identifier, "Expect field name.")?;
+        self.consume(&Equal, "Expect '=' after field name.")?;
+        let initializer = Some(Rc::new(self.expression()?));
+        self.consume(&Semicolon, "Expect ';' after field initializer.")?;
+        Ok(Rc::new(Stmt::Var(Rc::new(VarStmt { name, initializer }))))
+    }
+
     fn statement(&mut self) -> Result<Rc<Stmt>, LoxResult> {
+        let start = self.current;
+        let stmt = self.unspanned_statement()?;
+        Ok(self.spanned_stmt(start, stmt))
+    }
+
+    fn unspanned_statement(&mut self) -> Result<Rc<Stmt>, LoxResult> {
         if match_token!(self, For) {
             return self.for_statement();
         }
@@ -180,18 +330,33 @@ impl Parser {
         }
 
         if match_token!(self, While) {
-            return Ok(Rc::new(self.while_statement()?));
+            return self.while_statement();
+        }
+
+        if match_token!(self, Break) {
+            let keyword = self.previous();
+            self.consume(&Semicolon, "Expect ';' after 'break'.")?;
+            return Ok(Rc::new(Stmt::Break(Rc::new(BreakStmt { keyword }))));
+        }
+
+        if match_token!(self, Continue) {
+            let keyword = self.previous();
+            self.consume(&Semicolon, "Expect ';' after 'continue'.")?;
+            return Ok(Rc::new(Stmt::Continue(Rc::new(ContinueStmt { keyword }))));
         }
 
         if match_token!(self, LeftBrace) {
+            let line = self.previous().line;
             return Ok(Rc::new(Stmt::Block(Rc::new(BlockStmt {
                 statements: Rc::new(self.block()?),
+                line,
             }))));
         }
         self.expression_statement()
     }
 
     fn for_statement(&mut self) -> Result<Rc<Stmt>, LoxResult> {
+        let line = self.previous().line;
         self.consume(&LeftParen, "Expect '(' after 'for'.")?;
 
         let initializer = if match_token!(self, Semicolon) {
@@ -202,12 +367,13 @@ impl Parser {
             Some(self.expression_statement()?)
         };
 
-        let condition = if !self.check(&Semicolon) {
-            self.expression()?
+        let (condition, binding) = if !self.check(&Semicolon) {
+            self.loop_condition()?
         } else {
-            Expr::Literal(Rc::new(LiteralExpr {
+            let always = Expr::Literal(Rc::new(LiteralExpr {
                 value: Some(Object::Bool(true)),
-            }))
+            }));
+            (always, None)
         };
 
         self.consume(&Semicolon, "Expect ';' after loop condition")?;
@@ -220,34 +386,50 @@ impl Parser {
 
         self.consume(&RightParen, "Expect ')' after for clauses")?;
 
-        let mut body = self.statement()?;
+        let body = self.statement()?;
 
-        if let Some(inc) = increment {
-            body = Rc::new(Stmt::Block(Rc::new(BlockStmt {
-                statements: Rc::new(vec![
-                    body,
-                    Rc::new(Stmt::Expression(Rc::new(ExpressionStmt {
-                        expression: Rc::new(inc),
-                    }))),
-                ]),
-            })));
-        };
+        // The increment is kept apart from the body so that `continue` still runs it. Variables
+        // declared in the initializer or the condition get a fresh binding for every iteration,
+        // so closures created in the body each capture their own copy.
+        let declarations: Vec<Rc<Stmt>> = initializer.into_iter().chain(binding).collect();
+        let loop_variables = declared_variables(&declarations);
 
-        body = Rc::new(Stmt::While(Rc::new(WhileStmt {
+        let body = Rc::new(Stmt::While(Rc::new(WhileStmt {
             condition: Rc::new(condition),
             body,
+            increment: increment.map(Rc::new),
+            loop_variables,
+            line,
         })));
 
-        if let Some(init) = initializer {
-            body = Rc::new(Stmt::Block(Rc::new(BlockStmt {
-                statements: Rc::new(vec![init, body]),
-            })));
-        };
+        Ok(enclose_loop(declarations, body, line))
+    }
 
-        Ok(body)
+    // A loop condition, which may declare a variable like `var line = read()`. The variable is
+    // assigned every time the condition is evaluated, so it is returned as the condition together
+    // with the declaration that has to come before the loop.
+    fn loop_condition(&mut self) -> Result<(Expr, Option<Rc<Stmt>>), LoxResult> {
+        if !match_token!(self, Var) {
+            return Ok((self.expression()?, None));
+        }
+
+        let name = self.consume(&Identifier, "Expect variable name.")?;
+        self.consume(&Equal, "Expect '=' after variable name in loop condition.")?;
+        let value = self.expression()?;
+
+        let declaration = Rc::new(Stmt::Var(Rc::new(VarStmt {
+            name: name.clone(),
+            initializer: None,
+        })));
+        let condition = Expr::Assign(Rc::new(AssignExpr {
+            name,
+            value: Rc::new(value),
+        }));
+        Ok((condition, Some(declaration)))
     }
 
     fn if_statement(&mut self) -> Result<Stmt, LoxResult> {
+        let line = self.previous().line;
         self.consume(&LeftParen, "Expect '(' after 'if'.")?;
         let condition = self.expression()?;
         self.consume(&RightParen, "Expect ')' after if condition.")?;
@@ -263,14 +445,17 @@ impl Parser {
             condition: Rc::new(condition),
             then_branch,
             else_branch,
+            line,
         })))
     }
 
     fn print_statement(&mut self) -> Result<Stmt, LoxResult> {
+        let line = self.previous().line;
         let value = self.expression()?;
         self.consume(&Semicolon, "Expect ';' after value.")?;
         Ok(Stmt::Print(Rc::new(PrintStmt {
             expression: Rc::new(value),
+            line,
         })))
     }
 
@@ -300,29 +485,52 @@ impl Parser {
         Ok(Rc::new(Stmt::Var(Rc::new(VarStmt { name, initializer }))))
     }
 
-    fn while_statement(&mut self) -> Result<Stmt, LoxResult> {
+    fn while_statement(&mut self) -> Result<Rc<Stmt>, LoxResult> {
+        let line = self.previous().line;
         self.consume(&LeftParen, "Expect '(' after 'while'.")?;
-        let condition = self.expression()?;
+        let (condition, binding) = self.loop_condition()?;
         self.consume(&RightParen, "Expect ')' after condition.")?;
 
         let body = self.statement()?;
 
-        Ok(Stmt::While(Rc::new(WhileStmt {
+        let declarations: Vec<Rc<Stmt>> = binding.into_iter().collect();
+        let loop_variables = declared_variables(&declarations);
+
+        let body = Rc::new(Stmt::While(Rc::new(WhileStmt {
             condition: Rc::new(condition),
             body,
-        })))
+            increment: None,
+            loop_variables,
+            line,
+        })));
+
+        Ok(enclose_loop(declarations, body, line))
     }
 
     fn expression_statement(&mut self) -> Result<Rc<Stmt>, LoxResult> {
+        let line = self.peek().line;
         let expr = self.expression()?;
         self.consume(&Semicolon, "Expect ';' after expression.")?;
         Ok(Rc::new(Stmt::Expression(Rc::new(ExpressionStmt {
             expression: Rc::new(expr),
+            line,
         }))))
     }
 
     fn function(&mut self, kind: &str) -> Result<Rc<Stmt>, LoxResult> {
         let name = self.consume(&Identifier, &format!("Expect {kind} name."))?;
+
+        // A method without a parameter list is a getter, it runs when the property is read.
+        if kind == "method" && match_token!(self, LeftBrace) {
+            let body = self.block()?;
+            return Ok(Rc::new(Stmt::Function(Rc::new(FunctionStmt {
+                name,
+                params: Rc::new(Vec::new()),
+                body: Rc::new(body),
+                is_getter: true,
+            }))));
+        }
+
         self.consume(&LeftParen, &format!("Expect '(' after {kind} name."))?;
 
         let mut params = Vec::new();
@@ -344,6 +552,7 @@ impl Parser {
             name,
             params: Rc::new(params),
             body: Rc::new(body),
+            is_getter: false,
         }))))
     }
 
@@ -358,23 +567,34 @@ impl Parser {
     }
 
     fn assignment(&mut self) -> Result<Expr, LoxResult> {
-        let expr = self.or()?;
+        let start = self.current;
+        let expr = self.ternary()?;
 
         if match_token!(self, Equal) {
             let equals = self.previous();
             let value = self.assignment()?;
 
             if let Expr::Variable(expr) = expr {
-                return Ok(Expr::Assign(Rc::new(AssignExpr {
+                let assign = Expr::Assign(Rc::new(AssignExpr {
                     name: expr.name.clone(),
                     value: Rc::new(value),
-                })));
+                }));
+                return Ok(self.spanned_expr(start, assign));
             } else if let Expr::Get(expr) = expr {
-                return Ok(Expr::Set(Rc::new(SetExpr {
+                let set = Expr::Set(Rc::new(SetExpr {
                     object: Rc::clone(&expr.object),
                     name: expr.name.clone(),
                     value: Rc::new(value),
-                })));
+                }));
+                return Ok(self.spanned_expr(start, set));
+            } else if let Expr::Index(expr) = expr {
+                let set = Expr::IndexSet(Rc::new(IndexSetExpr {
+                    object: Rc::clone(&expr.object),
+                    bracket: expr.bracket.clone(),
+                    index: Rc::clone(&expr.index),
+                    value: Rc::new(value),
+                }));
+                return Ok(self.spanned_expr(start, set));
             };
             self.error(&equals, "Invalid assignment target");
         }
@@ -382,116 +602,83 @@ impl Parser {
         Ok(expr)
     }
 
-    fn or(&mut self) -> Result<Expr, LoxResult> {
-        let mut expr = self.and()?;
-
-        while match_token!(self, Or) {
-            let operator = self.previous();
-            let right = self.and()?;
-            expr = Expr::Logical(Rc::new(LogicalExpr {
-                left: Rc::new(expr),
-                operator,
-                right: Rc::new(right),
-            }));
-        }
-
-        Ok(expr)
-    }
-
-    fn and(&mut self) -> Result<Expr, LoxResult> {
-        let mut expr = self.equality()?;
-
-        while match_token!(self, And) {
-            let operator = self.previous();
-            let right = self.equality()?;
-            expr = Expr::Logical(Rc::new(LogicalExpr {
-                left: Rc::new(expr),
-                operator,
-                right: Rc::new(right),
-            }));
-        }
-
-        Ok(expr)
-    }
-
-    fn equality(&mut self) -> Result<Expr, LoxResult> {
-        let mut expr = self.comparison()?;
-
-        while match_token!(self, BangEqual, EqualEqual) {
-            let operator = self.previous();
-            let right = self.comparison()?;
-            expr = Expr::Binary(Rc::new(BinaryExpr {
-                left: Rc::new(expr),
-                operator,
-                right: Rc::new(right),
-            }));
-        }
-
-        Ok(expr)
-    }
-
-    fn comparison(&mut self) -> Result<Expr, LoxResult> {
-        let mut expr = self.term()?;
+    // `condition ? then : else`, binding looser than `or`. The else branch is parsed as another
+    // ternary, so `a ? b : c ? d : e` is `a ? b : (c ? d : e)`.
+    fn ternary(&mut self) -> Result<Expr, LoxResult> {
+        let start = self.current;
+        let condition = self.binary(0)?;
 
-        while match_token!(self, Greater, GreaterEqual, Less, LessEqual) {
-            let operator = self.previous();
-            let right = self.term()?;
-            expr = Expr::Binary(Rc::new(BinaryExpr {
-                left: Rc::new(expr),
-                operator,
-                right: Rc::new(right),
-            }));
+        if !match_token!(self, Question) {
+            return Ok(condition);
         }
+        let then_branch = self.expression()?;
+        self.consume(&Colon, "Expect ':' after then branch of conditional expression.")?;
+        let else_branch = self.ternary()?;
 
-        Ok(expr)
-    }
-
-    fn term(&mut self) -> Result<Expr, LoxResult> {
-        let mut expr = self.factor()?;
-
-        while match_token!(self, Minus, Plus) {
-            let operator = self.previous();
-            let right = self.factor()?;
-            expr = Expr::Binary(Rc::new(BinaryExpr {
-                left: Rc::new(expr),
-                operator,
-                right: Rc::new(right),
-            }))
-        }
-        Ok(expr)
+        let ternary = Expr::Ternary(Rc::new(TernaryExpr {
+            condition: Rc::new(condition),
+            then_branch: Rc::new(then_branch),
+            else_branch: Rc::new(else_branch),
+        }));
+        Ok(self.spanned_expr(start, ternary))
     }
 
-    fn factor(&mut self) -> Result<Expr, LoxResult> {
+    // Precedence climbing over the table in `binary_operator`: parses operands and every operator
+    // that binds at least as tightly as `min_power`.
+    fn binary(&mut self, min_power: u8) -> Result<Expr, LoxResult> {
+        let start = self.current;
         let mut expr = self.unary()?;
 
-        while match_token!(self, Star, Slash) {
-            let operator = self.previous();
-            let right = self.unary()?;
-            expr = Expr::Binary(Rc::new(BinaryExpr {
-                left: Rc::new(expr),
-                operator,
-                right: Rc::new(right),
-            }))
+        while let Some((power, associativity)) = binary_operator(&self.peek().token_type) {
+            if power < min_power {
+                break;
+            }
+            let operator = self.advance();
+            // a left-associative operator only lets tighter operators into its right operand, a
+            // right-associative one also lets in another operator of its own power
+            let right = match associativity {
+                Associativity::Left => self.binary(power + 1)?,
+                Associativity::Right => self.binary(power)?,
+            };
+
+            expr = if matches!(operator.token_type, Or | And) {
+                Expr::Logical(Rc::new(LogicalExpr {
+                    left: Rc::new(expr),
+                    operator,
+                    right: Rc::new(right),
+                }))
+            } else {
+                Expr::Binary(Rc::new(BinaryExpr {
+                    left: Rc::new(expr),
+                    operator,
+                    right: Rc::new(right),
+                }))
+            };
+            expr = self.spanned_expr(start, expr);
         }
 
         Ok(expr)
     }
 
     fn unary(&mut self) -> Result<Expr, LoxResult> {
+        let start = self.current;
         if match_token!(self, Bang, Minus) {
             let operator = self.previous();
             let right = self.unary()?;
-            Ok(Expr::Unary(Rc::new(UnaryExpr {
+            let unary = Expr::Unary(Rc::new(UnaryExpr {
                 operator,
                 right: Rc::new(right),
-            })))
+            }));
+            Ok(self.spanned_expr(start, unary))
         } else {
             self.call()
         }
     }
 
     fn call(&mut self) -> Result<Expr, LoxResult> {
-        let mut expr = self.primary()?;
+        let start = self.current;
+        let primary = self.primary()?;
+        let mut expr = self.spanned_expr(start, primary);
 
         loop {
             if match_token!(self, LeftParen) {
@@ -502,9 +689,18 @@ impl Parser {
                     object: Rc::new(expr),
                     name,
                 }))
+            } else if match_token!(self, LeftBracket) {
+                let index = self.expression()?;
+                let bracket = self.consume(&RightBracket, "Expect ']' after index.")?;
+                expr = Expr::Index(Rc::new(IndexExpr {
+                    object: Rc::new(expr),
+                    bracket,
+                    index: Rc::new(index),
+                }))
             } else {
                 break;
             }
+            expr = self.spanned_expr(start, expr);
         }
 
         Ok(expr)
@@ -564,6 +760,37 @@ impl Parser {
             })));
         }
 
+        if match_token!(self, LeftBracket) {
+            let open_bracket = self.previous();
+            let mut elements = Vec::new();
+            if !self.check(&RightBracket) {
+                elements.push(Rc::new(self.expression()?));
+                while match_token!(self, Comma) {
+                    elements.push(Rc::new(self.expression()?));
+                }
+            }
+            let bracket = self.consume(&RightBracket, "Expect ']' after array elements.")?;
+            return Ok(Expr::Array(Rc::new(ArrayExpr { open_bracket, bracket, elements })));
+        }
+
+        // Only reached in expression position, a `{` starting a statement is a block.
+        if match_token!(self, LeftBrace) {
+            let open_brace = self.previous();
+            let (mut keys, mut values) = (Vec::new(), Vec::new());
+            if !self.check(&RightBrace) {
+                loop {
+                    keys.push(Rc::new(self.expression()?));
+                    self.consume(&Colon, "Expect ':' after map key.")?;
+                    values.push(Rc::new(self.expression()?));
+                    if !match_token!(self, Comma) {
+                        break;
+                    }
+                }
+            }
+            let brace = self.consume(&RightBrace, "Expect '}' after map entries.")?;
+            return Ok(Expr::Map(Rc::new(MapExpr { open_brace, brace, keys, values })));
+        }
+
         if match_token!(self, This) {
             return Ok(Expr::This(Rc::new(ThisExpr {
                 keyword: self.previous(),
@@ -584,12 +811,169 @@ impl Parser {
             })));
         }
 
-        Err(LoxResult::parse_error(
-            &self.tokens[self.current],
-            "Expect Expression",
-        ))
+        Err(LoxResult::parse_error(&self.peek(), "Expect Expression"))
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn token(token_type: TokenType, lexeme: &str, literal: Option<Object>) -> Token {
+        Token::new(token_type, lexeme.to_string(), literal, 1, 0)
+    }
+
+    #[test]
+    fn parses_tokens_without_eof() {
+        let tokens = vec![
+            token(Print, "print", None),
+            token(NumberLiteral, "1", Some(Object::Num(1.0))),
+            token(Semicolon, ";", None),
+        ];
+        let mut parser = Parser::new(tokens);
+        assert_eq!(parser.parse().unwrap().len(), 1);
+        assert!(parser.success());
+    }
+
+    #[test]
+    fn incomplete_statement_without_eof_errors() {
+        let tokens = vec![
+            token(Var, "var", None),
+            token(Identifier, "x", None),
+            token(Equal, "=", None),
+        ];
+        let mut parser = Parser::new(tokens);
+        assert!(parser.parse().is_err());
+    }
+
+    #[test]
+    fn parses_empty_token_list() {
+        let mut parser = Parser::new(vec![]);
+        assert!(parser.parse().unwrap().is_empty());
+    }
+
+    #[test]
+    fn parse_one_statement_at_a_time() {
+        let tokens = crate::scanner::Scanner::new("var a = 1; print a; fun f() {}").tokenize().unwrap();
+        let mut parser = Parser::new(tokens);
+
+        assert!(matches!(parser.parse_one().unwrap().as_deref(), Some(Stmt::Var(_))));
+        assert!(matches!(parser.parse_one().unwrap().as_deref(), Some(Stmt::Print(_))));
+        assert!(matches!(parser.parse_one().unwrap().as_deref(), Some(Stmt::Function(_))));
+        assert!(parser.parse_one().unwrap().is_none());
+        assert!(parser.parse_one().unwrap().is_none());
+    }
+
+    #[test]
+    fn parse_one_recovers_after_error() {
+        let tokens = crate::scanner::Scanner::new("var = 1; print 2;").tokenize().unwrap();
+        let mut parser = Parser::new(tokens);
+
+        assert!(parser.parse_one().is_err());
+        assert!(matches!(parser.parse_one().unwrap().as_deref(), Some(Stmt::Print(_))));
+        assert!(parser.parse_one().unwrap().is_none());
+        assert!(!parser.success());
+    }
+
+    fn sexpr(expr: &Expr) -> String {
+        match expr {
+            Expr::Binary(e) => format!("({} {} {})", e.operator.lexeme, sexpr(&e.left), sexpr(&e.right)),
+            Expr::Logical(e) => format!("({} {} {})", e.operator.lexeme, sexpr(&e.left), sexpr(&e.right)),
+            Expr::Unary(e) => format!("({} {})", e.operator.lexeme, sexpr(&e.right)),
+            Expr::Grouping(e) => format!("(group {})", sexpr(&e.expression)),
+            Expr::Literal(e) => e.value.as_ref().map_or("nil".to_string(), |v| v.to_string()),
+            Expr::Variable(e) => e.name.lexeme.clone(),
+            Expr::Ternary(e) => format!(
+                "(? {} {} {})",
+                sexpr(&e.condition),
+                sexpr(&e.then_branch),
+                sexpr(&e.else_branch)
+            ),
+            _ => panic!("sexpr: unsupported expression"),
+        }
+    }
+
+    fn parse_expression(source: &str) -> String {
+        let tokens = crate::scanner::Scanner::new(&format!("{source};")).tokenize().unwrap();
+        let statements = Parser::new(tokens).parse().unwrap();
+        match &*statements[0] {
+            Stmt::Expression(stmt) => sexpr(&stmt.expression),
+            _ => panic!("expected an expression statement"),
+        }
     }
+
+    #[test]
+    fn factor_binds_tighter_than_term() {
+        assert_eq!(parse_expression("1 + 2 * 3"), "(+ 1 (* 2 3))");
+        assert_eq!(parse_expression("1 * 2 - 3 / 4"), "(- (* 1 2) (/ 3 4))");
+    }
+
+    #[test]
+    fn binary_operators_are_left_associative() {
+        assert_eq!(parse_expression("1 - 2 - 3"), "(- (- 1 2) 3)");
+        assert_eq!(parse_expression("8 / 4 / 2"), "(/ (/ 8 4) 2)");
+    }
+
+    #[test]
+    fn comparison_binds_tighter_than_equality() {
+        assert_eq!(parse_expression("1 < 2 == 3 >= 4"), "(== (< 1 2) (>= 3 4))");
+        assert_eq!(parse_expression("1 + 1 > 1"), "(> (+ 1 1) 1)");
+    }
+
+    #[test]
+    fn and_binds_tighter_than_or() {
+        assert_eq!(parse_expression("a or b and c"), "(or a (and b c))");
+        assert_eq!(parse_expression("a and b == c or d"), "(or (and a (== b c)) d)");
+    }
+
+    #[test]
+    fn ternary_is_right_associative_and_binds_loosest() {
+        assert_eq!(parse_expression("a ? 1 : 2"), "(? a 1 2)");
+        assert_eq!(parse_expression("a or b ? 1 + 1 : 2"), "(? (or a b) (+ 1 1) 2)");
+        assert_eq!(parse_expression("a ? b ? 1 : 2 : c ? 3 : 4"), "(? a (? b 1 2) (? c 3 4))");
+    }
+
+    #[test]
+    fn unary_and_grouping_bind_tightest() {
+        assert_eq!(parse_expression("-1 * !a"), "(* (- 1) (! a))");
+        assert_eq!(parse_expression("(1 + 2) * 3"), "(* (group (+ 1 2)) 3)");
+    }
+
+    #[test]
+    fn binary_span_covers_operands_and_operator() {
+        let tokens = crate::scanner::Scanner::new("var x =\n  1 +\n  2;").tokenize().unwrap();
+        let mut parser = Parser::new(tokens);
+        let (statements, spans) = parser.parse_with_spans().unwrap();
+
+        let Stmt::Var(var) = &*statements[0] else {
+            panic!("expected a var statement");
+        };
+        let initializer = var.initializer.as_ref().unwrap();
+        let Expr::Binary(binary) = &**initializer else {
+            panic!("expected a binary expression");
+        };
+
+        let span = spans.expr(initializer).unwrap();
+        let left = spans.expr(&binary.left).unwrap();
+        let right = spans.expr(&binary.right).unwrap();
+        assert_eq!(span, Span { start: left.start, end: right.end });
+        assert_eq!(parser.tokens()[span.start].line, 2);
+        assert_eq!(parser.tokens()[span.end].line, 3);
+        assert_eq!(parser.tokens()[left.end + 1].lexeme, "+");
+
+        // the statement runs from `var` to the `;`
+        assert_eq!(spans.stmt(&statements[0]), Some(Span { start: 0, end: 6 }));
+    }
+
+    #[test]
+    fn spans_are_only_collected_on_request() {
+        let tokens = crate::scanner::Scanner::new("print 1 + 2;").tokenize().unwrap();
+        let mut parser = Parser::new(tokens);
+        parser.parse().unwrap();
+        assert!(parser.spans.is_none());
+    }
+
 }
+
 /*
 #[cfg(test)]
 mod tests {
@@ -0,0 +1,160 @@
+use crate::error::*;
+use crate::expr::*;
+use crate::token::Token;
+use std::rc::Rc;
+
+// Tells whether evaluating an expression can have side effects. Pure expressions can be evaluated
+// again, left out or folded without changing what a program does, though they may still fail with
+// a runtime error. This is conservative: anything that may run code is treated as impure, which
+// includes property reads since those can call a getter.
+pub struct Purity;
+
+impl Purity {
+    pub fn is_pure(expr: &Rc<Expr>) -> bool {
+        // the visitor never fails, the `Result` only comes from the visitor trait
+        expr.accept(Rc::clone(expr), &Purity).unwrap_or(false)
+    }
+
+    fn all_pure(exprs: &[Rc<Expr>]) -> bool {
+        exprs.iter().all(Purity::is_pure)
+    }
+}
+
+impl Expr {
+    // The leftmost token the expression holds on to, which is where a warning about it points.
+    // Literals keep no token, so this is the `+` of `1 + x`, and `None` for a lone literal.
+    pub fn first_token(&self) -> Option<&Token> {
+        match self {
+            Expr::Array(e) => Some(&e.open_bracket),
+            Expr::Assign(e) => Some(&e.name),
+            Expr::Binary(e) => e.left.first_token().or(Some(&e.operator)),
+            Expr::Call(e) => e.callee.first_token().or(Some(&e.paren)),
+            Expr::Get(e) => e.object.first_token().or(Some(&e.name)),
+            Expr::Grouping(e) => e.expression.first_token(),
+            Expr::Index(e) => e.object.first_token().or(Some(&e.bracket)),
+            Expr::IndexSet(e) => e.object.first_token().or(Some(&e.bracket)),
+            Expr::Literal(_) => None,
+            Expr::Logical(e) => e.left.first_token().or(Some(&e.operator)),
+            Expr::Map(e) => Some(&e.open_brace),
+            Expr::Set(e) => e.object.first_token().or(Some(&e.name)),
+            Expr::Super(e) => Some(&e.keyword),
+            Expr::Ternary(e) => e
+                .condition
+                .first_token()
+                .or_else(|| e.then_branch.first_token())
+                .or_else(|| e.else_branch.first_token()),
+            Expr::This(e) => Some(&e.keyword),
+            Expr::Unary(e) => Some(&e.operator),
+            Expr::Variable(e) => Some(&e.name),
+        }
+    }
+}
+
+impl ExprVisitor<bool> for Purity {
+    fn visit_array_expr(&self, _: Rc<Expr>, expr: &ArrayExpr) -> Result<bool, LoxResult> {
+        Ok(Purity::all_pure(&expr.elements))
+    }
+
+    fn visit_assign_expr(&self, _: Rc<Expr>, _: &AssignExpr) -> Result<bool, LoxResult> {
+        Ok(false)
+    }
+
+    fn visit_binary_expr(&self, _: Rc<Expr>, expr: &BinaryExpr) -> Result<bool, LoxResult> {
+        Ok(Purity::is_pure(&expr.left) && Purity::is_pure(&expr.right))
+    }
+
+    fn visit_call_expr(&self, _: Rc<Expr>, _: &CallExpr) -> Result<bool, LoxResult> {
+        Ok(false)
+    }
+
+    fn visit_get_expr(&self, _: Rc<Expr>, _: &GetExpr) -> Result<bool, LoxResult> {
+        Ok(false)
+    }
+
+    fn visit_grouping_expr(&self, _: Rc<Expr>, expr: &GroupingExpr) -> Result<bool, LoxResult> {
+        Ok(Purity::is_pure(&expr.expression))
+    }
+
+    fn visit_index_expr(&self, _: Rc<Expr>, expr: &IndexExpr) -> Result<bool, LoxResult> {
+        Ok(Purity::is_pure(&expr.object) && Purity::is_pure(&expr.index))
+    }
+
+    fn visit_index_set_expr(&self, _: Rc<Expr>, _: &IndexSetExpr) -> Result<bool, LoxResult> {
+        Ok(false)
+    }
+
+    fn visit_literal_expr(&self, _: Rc<Expr>, _: &LiteralExpr) -> Result<bool, LoxResult> {
+        Ok(true)
+    }
+
+    fn visit_logical_expr(&self, _: Rc<Expr>, expr: &LogicalExpr) -> Result<bool, LoxResult> {
+        Ok(Purity::is_pure(&expr.left) && Purity::is_pure(&expr.right))
+    }
+
+    fn visit_map_expr(&self, _: Rc<Expr>, expr: &MapExpr) -> Result<bool, LoxResult> {
+        Ok(Purity::all_pure(&expr.keys) && Purity::all_pure(&expr.values))
+    }
+
+    fn visit_set_expr(&self, _: Rc<Expr>, _: &SetExpr) -> Result<bool, LoxResult> {
+        Ok(false)
+    }
+
+    // `super.name` can be a getter as well
+    fn visit_super_expr(&self, _: Rc<Expr>, _: &SuperExpr) -> Result<bool, LoxResult> {
+        Ok(false)
+    }
+
+    fn visit_ternary_expr(&self, _: Rc<Expr>, expr: &TernaryExpr) -> Result<bool, LoxResult> {
+        Ok(Purity::is_pure(&expr.condition)
+            && Purity::is_pure(&expr.then_branch)
+            && Purity::is_pure(&expr.else_branch))
+    }
+
+    fn visit_this_expr(&self, _: Rc<Expr>, _: &ThisExpr) -> Result<bool, LoxResult> {
+        Ok(true)
+    }
+
+    fn visit_unary_expr(&self, _: Rc<Expr>, expr: &UnaryExpr) -> Result<bool, LoxResult> {
+        Ok(Purity::is_pure(&expr.right))
+    }
+
+    fn visit_variable_expr(&self, _: Rc<Expr>, _: &VariableExpr) -> Result<bool, LoxResult> {
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::Parser;
+    use crate::scanner::Scanner;
+    use crate::stmt::Stmt;
+
+    fn pure(source: &str) -> bool {
+        let tokens = Scanner::new(&format!("{source};")).tokenize().unwrap();
+        let statements = Parser::new(tokens).parse().unwrap();
+        match &*statements[0] {
+            Stmt::Expression(stmt) => Purity::is_pure(&stmt.expression),
+            _ => panic!("expected an expression statement"),
+        }
+    }
+
+    #[test]
+    fn arithmetic_and_variables_are_pure() {
+        assert!(pure("1 + 2 * x"));
+        assert!(pure("-(a or b) == !c"));
+        assert!(pure("[x, {\"k\": y}][0]"));
+        assert!(pure("x ? y : 1"));
+    }
+
+    #[test]
+    fn calls_and_assignments_are_impure() {
+        assert!(!pure("f()"));
+        assert!(!pure("x = 1"));
+        assert!(!pure("1 + f()"));
+        assert!(!pure("a.b = 1"));
+        assert!(!pure("a[0] = 1"));
+        assert!(!pure("[g()]"));
+        assert!(!pure("x ? f() : 1"));
+    }
+}
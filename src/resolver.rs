@@ -6,6 +6,7 @@ use std::rc::Rc;
 use crate::error::*;
 use crate::expr::*;
 use crate::interpreter::*;
+use crate::purity::Purity;
 use crate::stmt::*;
 use crate::token::*;
 
@@ -22,14 +23,29 @@ enum ClassType {
     None,
     Subclass,
     Class,
+    StaticMethod,
+}
+
+#[derive(PartialEq, Clone, Copy)]
+enum VarState {
+    // Declared, but its initializer is still being resolved.
+    Declared,
+    // Declared with `var a;` and not assigned to yet, reading it gives nil.
+    Uninitialized,
+    Initialized,
 }
 
 pub struct Resolver<'a> {
     interpreter: &'a Interpreter,
-    scopes: RefCell<Vec<HashMap<String, bool>>>,
+    scopes: RefCell<Vec<HashMap<String, VarState>>>,
     had_error: RefCell<bool>,
     current_function: RefCell<FunctionType>,
+    current_loop: RefCell<bool>,
     current_class: RefCell<ClassType>,
+    warn_uninitialized: bool,
+    forbid_init_calls: bool,
+    warn_unused_results: bool,
+    warnings: RefCell<Vec<String>>,
 }
 
 impl<'a> StmtVisitor<()> for Resolver<'a> {
@@ -48,18 +64,39 @@ impl<'a> StmtVisitor<()> for Resolver<'a> {
             self.resolve_expr(superclass.clone())?;
 
             self.begin_scope();
-            self.scopes.borrow_mut().last_mut().unwrap().insert("super".to_string(), true);
+            self.scopes.borrow_mut().last_mut().unwrap().insert("super".to_string(), VarState::Initialized);
+        }
+
+        // Static methods aren't bound to an instance, so they're resolved outside the scope
+        // holding `this`.
+        let class_type = self.current_class.replace(ClassType::StaticMethod);
+        for method in stmt.static_methods.deref() {
+            if let Stmt::Function(method) = method.deref() {
+                self.resolve_function(method, FunctionType::Method)?;
+            }
         }
+        self.current_class.replace(class_type);
 
         self.begin_scope();
         self.scopes
             .borrow_mut()
             .last_mut()
             .unwrap()
-            .insert("this".to_string(), true);
+            .insert("this".to_string(), VarState::Initialized);
+
+        for field in stmt.fields.deref() {
+            if let Stmt::Var(field) = field.deref() {
+                if let Some(initializer) = &field.initializer {
+                    self.resolve_expr(initializer.clone())?;
+                }
+            }
+        }
 
         for method in stmt.methods.deref() {
             if let Stmt::Function(method) = method.deref() {
+                if method.is_getter && method.name.lexeme == "init" {
+                    self.error(&method.name, "An initializer can't be a getter.");
+                }
                 let declaration = if method.name.lexeme == "init" {
                     FunctionType::Initializer
                 } else {
@@ -85,6 +122,14 @@ impl<'a> StmtVisitor<()> for Resolver<'a> {
     }
 
     fn visit_expression_stmt(&self, _: Rc<Stmt>, stmt: &ExpressionStmt) -> Result<(), LoxResult> {
+        if self.warn_unused_results && Purity::is_pure(&stmt.expression) {
+            let message = "Expression result is unused.";
+            match stmt.expression.first_token() {
+                Some(token) => self.warning(token, message),
+                // a lone literal has no token to point at
+                None => self.push_warning(format!("Warning: {}", message)),
+            }
+        }
         self.resolve_expr(stmt.expression.clone())
     }
 
@@ -129,16 +174,48 @@ impl<'a> StmtVisitor<()> for Resolver<'a> {
 
         if let Some(initializer) = &stmt.initializer {
             self.resolve_expr(initializer.clone())?;
+            self.define(&stmt.name);
+        } else {
+            self.set_state(&stmt.name, VarState::Uninitialized);
         }
 
-        self.define(&stmt.name);
-
         Ok(())
     }
 
     fn visit_while_stmt(&self, _: Rc<Stmt>, stmt: &WhileStmt) -> Result<(), LoxResult> {
         self.resolve_expr(stmt.condition.clone())?;
-        self.resolve_stmt(stmt.body.clone())?;
+
+        let enclosing_loop = self.current_loop.replace(true);
+        if !stmt.loop_variables.is_empty() {
+            self.begin_scope();
+            for name in &stmt.loop_variables {
+                self.declare(name);
+                self.define(name);
+            }
+            self.resolve_stmt(stmt.body.clone())?;
+            self.end_scope();
+        } else {
+            self.resolve_stmt(stmt.body.clone())?;
+        }
+        self.current_loop.replace(enclosing_loop);
+
+        if let Some(increment) = &stmt.increment {
+            self.resolve_expr(increment.clone())?;
+        }
+        Ok(())
+    }
+
+    fn visit_break_stmt(&self, _: Rc<Stmt>, stmt: &BreakStmt) -> Result<(), LoxResult> {
+        if !*self.current_loop.borrow() {
+            self.error(&stmt.keyword, "Can't use 'break' outside of a loop.");
+        }
+        Ok(())
+    }
+
+    fn visit_continue_stmt(&self, _: Rc<Stmt>, stmt: &ContinueStmt) -> Result<(), LoxResult> {
+        if !*self.current_loop.borrow() {
+            self.error(&stmt.keyword, "Can't use 'continue' outside of a loop.");
+        }
         Ok(())
     }
 }
@@ -150,13 +227,56 @@ impl<'a> Resolver<'a> {
             scopes: RefCell::new(Vec::new()),
             had_error: RefCell::new(false),
             current_function: RefCell::new(FunctionType::None),
+            current_loop: RefCell::new(false),
             current_class: RefCell::new(ClassType::None),
+            warn_uninitialized: false,
+            forbid_init_calls: false,
+            warn_unused_results: false,
+            warnings: RefCell::new(Vec::new()),
         }
     }
 
+    // Warn about local variables that are read while they were only declared with `var a;` and
+    // not assigned to yet. This looks at the order of the code, not at how it runs, so a read in
+    // a function declared before the assignment warns as well. Warnings don't make resolving fail.
+    pub fn warn_uninitialized(mut self, warn: bool) -> Self {
+        self.warn_uninitialized = warn;
+        self
+    }
+
+    // Reject calling `init` on an instance, which runs the initializer again. `super.init()` is
+    // still allowed since a subclass initializer needs it.
+    pub fn forbid_init_calls(mut self, forbid: bool) -> Self {
+        self.forbid_init_calls = forbid;
+        self
+    }
+
+    // Warn about expression statements that can't have side effects, so dropping their value
+    // means the statement does nothing. Calls and assignments never warn.
+    pub fn warn_unused_results(mut self, warn: bool) -> Self {
+        self.warn_unused_results = warn;
+        self
+    }
+
+    pub fn warnings(&self) -> Vec<String> {
+        self.warnings.borrow().clone()
+    }
+
+    fn warning(&self, token: &Token, message: &str) {
+        self.push_warning(format!(
+            "[line: {}, col: {}] at '{}' Warning: {}",
+            token.line, token.column, token.lexeme, message
+        ));
+    }
+
+    fn push_warning(&self, warning: String) {
+        eprintln!("{}", warning);
+        self.warnings.borrow_mut().push(warning);
+    }
+
     fn error(&self, token: &Token, message: &str) {
         self.had_error.replace(true);
-        LoxResult::runtime_error(token, message);
+        LoxResult::runtime_error(token, message).report("");
     }
 
     pub fn success(&self) -> bool {
@@ -178,7 +298,7 @@ impl<'a> Resolver<'a> {
     fn begin_scope(&self) {
         self.scopes
             .borrow_mut()
-            .push(HashMap::<String, bool>::new());
+            .push(HashMap::<String, VarState>::new());
     }
 
     fn end_scope(&self) {
@@ -190,13 +310,38 @@ impl<'a> Resolver<'a> {
             if scope.contains_key(&name.lexeme.clone()) {
                 self.error(name, "Already a variable with this name in this scope.");
             }
-            scope.insert(name.lexeme.clone(), false);
+            scope.insert(name.lexeme.clone(), VarState::Declared);
         }
     }
 
     fn define(&self, name: &Token) {
+        self.set_state(name, VarState::Initialized);
+    }
+
+    fn set_state(&self, name: &Token, state: VarState) {
         if let Some(scope) = self.scopes.borrow_mut().last_mut() {
-            scope.insert(name.lexeme.clone(), true);
+            scope.insert(name.lexeme.clone(), state);
+        }
+    }
+
+    // The state of the variable `name` refers to, if it's a local.
+    fn local_state(&self, name: &Token) -> Option<VarState> {
+        self.scopes
+            .borrow()
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(&name.lexeme).copied())
+    }
+
+    fn mark_initialized(&self, name: &Token) {
+        if let Some(scope) = self
+            .scopes
+            .borrow_mut()
+            .iter_mut()
+            .rev()
+            .find(|scope| scope.contains_key(&name.lexeme))
+        {
+            scope.insert(name.lexeme.clone(), VarState::Initialized);
         }
     }
 
@@ -221,6 +366,7 @@ impl<'a> Resolver<'a> {
         function_type: FunctionType,
     ) -> Result<(), LoxResult> {
         let enclosing_function = self.current_function.replace(function_type);
+        let enclosing_loop = self.current_loop.replace(false);
         self.begin_scope();
         for param in function.params.deref() {
             self.declare(param);
@@ -229,6 +375,7 @@ impl<'a> Resolver<'a> {
         self.resolve(function.body.clone())?;
         self.end_scope();
         self.current_function.replace(enclosing_function);
+        self.current_loop.replace(enclosing_loop);
         Ok(())
     }
 }
@@ -242,6 +389,9 @@ impl<'a> ExprVisitor<()> for Resolver<'a> {
             ClassType::Class => {
                 self.error(&expr.keyword, "Can't use 'super' in a class with no superclass.");
             }
+            ClassType::StaticMethod => {
+                self.error(&expr.keyword, "Can't use 'super' in a static method.");
+            }
             ClassType::Subclass => {}
         }
         self.resolve_local(wrapper, &expr.keyword);
@@ -249,8 +399,10 @@ impl<'a> ExprVisitor<()> for Resolver<'a> {
     }
 
     fn visit_this_expr(&self, wrapper: Rc<Expr>, expr: &ThisExpr) -> Result<(), LoxResult> {
-        if *self.current_class.borrow() == ClassType::None {
-            self.error(&expr.keyword, "Can't use 'this' outside of a class.");
+        match *self.current_class.borrow() {
+            ClassType::None => self.error(&expr.keyword, "Can't use 'this' outside of a class."),
+            ClassType::StaticMethod => self.error(&expr.keyword, "Can't use 'this' in a static method."),
+            ClassType::Class | ClassType::Subclass => {}
         }
         self.resolve_local(wrapper, &expr.keyword);
         Ok(())
@@ -267,6 +419,34 @@ impl<'a> ExprVisitor<()> for Resolver<'a> {
         Ok(())
     }
 
+    fn visit_array_expr(&self, _: Rc<Expr>, expr: &ArrayExpr) -> Result<(), LoxResult> {
+        for element in &expr.elements {
+            self.resolve_expr(element.clone())?;
+        }
+        Ok(())
+    }
+
+    fn visit_map_expr(&self, _: Rc<Expr>, expr: &MapExpr) -> Result<(), LoxResult> {
+        for (key, value) in expr.keys.iter().zip(&expr.values) {
+            self.resolve_expr(key.clone())?;
+            self.resolve_expr(value.clone())?;
+        }
+        Ok(())
+    }
+
+    fn visit_index_expr(&self, _: Rc<Expr>, expr: &IndexExpr) -> Result<(), LoxResult> {
+        self.resolve_expr(expr.object.clone())?;
+        self.resolve_expr(expr.index.clone())?;
+        Ok(())
+    }
+
+    fn visit_index_set_expr(&self, _: Rc<Expr>, expr: &IndexSetExpr) -> Result<(), LoxResult> {
+        self.resolve_expr(expr.object.clone())?;
+        self.resolve_expr(expr.index.clone())?;
+        self.resolve_expr(expr.value.clone())?;
+        Ok(())
+    }
+
     fn visit_literal_expr(&self, _: Rc<Expr>, expr: &LiteralExpr) -> Result<(), LoxResult> {
         Ok(())
     }
@@ -291,28 +471,45 @@ impl<'a> ExprVisitor<()> for Resolver<'a> {
                 .last()
                 .unwrap()
                 .get(&expr.name.lexeme.clone())
-                == Some(&false)
+                == Some(&VarState::Declared)
         {
-            Err(LoxResult::runtime_error(
-                &expr.name,
-                "Can't read local variable in it's own initializer",
-            ))
+            let e = LoxResult::runtime_error(&expr.name, "Can't read local variable in it's own initializer");
+            e.report("");
+            Err(e)
         } else {
+            if self.warn_uninitialized && self.local_state(&expr.name) == Some(VarState::Uninitialized) {
+                self.warning(&expr.name, "Variable is read before it is assigned a value.");
+                // once per variable is enough
+                self.mark_initialized(&expr.name);
+            }
             self.resolve_local(wrapper, &expr.name);
             Ok(())
         }
     }
     fn visit_assign_expr(&self, wrapper: Rc<Expr>, expr: &AssignExpr) -> Result<(), LoxResult> {
         self.resolve_expr(expr.value.clone())?;
+        self.mark_initialized(&expr.name);
         self.resolve_local(wrapper, &expr.name);
         Ok(())
     }
+    fn visit_ternary_expr(&self, _: Rc<Expr>, expr: &TernaryExpr) -> Result<(), LoxResult> {
+        self.resolve_expr(expr.condition.clone())?;
+        self.resolve_expr(expr.then_branch.clone())?;
+        self.resolve_expr(expr.else_branch.clone())?;
+        Ok(())
+    }
+
     fn visit_logical_expr(&self, _: Rc<Expr>, expr: &LogicalExpr) -> Result<(), LoxResult> {
         self.resolve_expr(expr.left.clone())?;
         self.resolve_expr(expr.right.clone())?;
         Ok(())
     }
     fn visit_call_expr(&self, _: Rc<Expr>, expr: &CallExpr) -> Result<(), LoxResult> {
+        if let Expr::Get(get) = expr.callee.deref() {
+            if self.forbid_init_calls && get.name.lexeme == "init" {
+                self.error(&get.name, "Can't call 'init' directly, call the class instead.");
+            }
+        }
         self.resolve_expr(expr.callee.clone())?;
 
         for arg in &expr.arguments {
@@ -321,3 +518,131 @@ impl<'a> ExprVisitor<()> for Resolver<'a> {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::Parser;
+    use crate::scanner::Scanner;
+
+    fn warnings(source: &str) -> Vec<String> {
+        let interpreter = Interpreter::new();
+        let tokens = Scanner::new(source).tokenize().unwrap();
+        let statements = Rc::new(Parser::new(tokens).parse().unwrap());
+        let resolver = Resolver::new(&interpreter).warn_uninitialized(true);
+        resolver.resolve(statements).unwrap();
+        assert!(resolver.success());
+        resolver.warnings()
+    }
+
+    #[test]
+    fn read_of_uninitialized_variable_warns() {
+        let warnings = warnings("{\n  var a;\n  print a;\n  print a;\n}");
+        assert_eq!(warnings, ["[line: 3, col: 9] at 'a' Warning: Variable is read before it is assigned a value."]);
+    }
+
+    #[test]
+    fn initialized_variables_dont_warn() {
+        assert!(warnings("{ var a = nil; print a; }").is_empty());
+        assert!(warnings("{ var a; a = 1; print a; }").is_empty());
+        assert!(warnings("{ var a; { a = 1; } print a; }").is_empty());
+        // globals aren't tracked
+        assert!(warnings("var a; print a;").is_empty());
+    }
+
+    #[test]
+    fn warnings_are_opt_in() {
+        let interpreter = Interpreter::new();
+        let tokens = Scanner::new("{ var a; print a; }").tokenize().unwrap();
+        let statements = Rc::new(Parser::new(tokens).parse().unwrap());
+        let resolver = Resolver::new(&interpreter);
+        resolver.resolve(statements).unwrap();
+        assert!(resolver.warnings().is_empty());
+    }
+
+    fn resolves(source: &str) -> bool {
+        let interpreter = Interpreter::new();
+        let tokens = Scanner::new(source).tokenize().unwrap();
+        let statements = Rc::new(Parser::new(tokens).parse().unwrap());
+        let resolver = Resolver::new(&interpreter);
+        resolver.resolve(statements).unwrap();
+        resolver.success()
+    }
+
+    #[test]
+    fn break_and_continue_only_inside_loops() {
+        assert!(resolves("while (true) { break; }"));
+        assert!(resolves("for (;;) { if (true) continue; }"));
+        assert!(!resolves("break;"));
+        assert!(!resolves("continue;"));
+        assert!(!resolves("while (true) { fun f() { break; } }"));
+    }
+
+    #[test]
+    fn this_and_super_not_allowed_in_static_methods() {
+        assert!(resolves("class A { class make() { return A(); } }"));
+        assert!(!resolves("class A { class make() { return this; } }"));
+        assert!(!resolves("class A { hi() {} } class B < A { class make() { return super.hi; } }"));
+    }
+
+    #[test]
+    fn initializer_cannot_be_a_getter() {
+        assert!(resolves("class A { init() {} area { return 1; } }"));
+        assert!(!resolves("class A { init { this.w = 1; } }"));
+    }
+
+    #[test]
+    fn init_calls_rejected_when_forbidden() {
+        let resolves_strict = |source: &str| {
+            let interpreter = Interpreter::new();
+            let tokens = Scanner::new(source).tokenize().unwrap();
+            let statements = Rc::new(Parser::new(tokens).parse().unwrap());
+            let resolver = Resolver::new(&interpreter).forbid_init_calls(true);
+            resolver.resolve(statements).unwrap();
+            resolver.success()
+        };
+
+        let class = "class Foo { init(x) { this.x = x; } } ";
+        assert!(resolves(&format!("{class} var foo = Foo(1); foo.init(2);")));
+        assert!(!resolves_strict(&format!("{class} var foo = Foo(1); foo.init(2);")));
+        assert!(resolves_strict(&format!("{class} var foo = Foo(1);")));
+        assert!(resolves_strict(&format!("{class} class Bar < Foo {{ init() {{ super.init(1); }} }}")));
+    }
+
+    fn unused_result_warnings(source: &str) -> Vec<String> {
+        let interpreter = Interpreter::new();
+        let tokens = Scanner::new(source).tokenize().unwrap();
+        let statements = Rc::new(Parser::new(tokens).parse().unwrap());
+        let resolver = Resolver::new(&interpreter).warn_unused_results(true);
+        resolver.resolve(statements).unwrap();
+        assert!(resolver.success());
+        resolver.warnings()
+    }
+
+    #[test]
+    fn pure_expression_statement_warns() {
+        let warnings = unused_result_warnings("var x = 1; var y = 2;\nx == y;");
+        assert_eq!(warnings, ["[line: 2, col: 1] at 'x' Warning: Expression result is unused."]);
+        assert_eq!(unused_result_warnings("1 + 2;"), ["[line: 1, col: 3] at '+' Warning: Expression result is unused."]);
+        assert_eq!(unused_result_warnings("\"hi\";"), ["Warning: Expression result is unused."]);
+    }
+
+    #[test]
+    fn array_and_map_statements_warn_at_their_opening_token() {
+        let warn_at = |col: usize, lexeme: &str| {
+            vec![format!("[line: 1, col: {col}] at '{lexeme}' Warning: Expression result is unused.")]
+        };
+        assert_eq!(unused_result_warnings("var x; [1, x];"), warn_at(8, "["));
+        assert_eq!(unused_result_warnings("var x; [x, 1];"), warn_at(8, "["));
+        assert_eq!(unused_result_warnings("var x; ({\"a\": x});"), warn_at(9, "{"));
+        assert_eq!(unused_result_warnings("var x; true ? x : 1;"), warn_at(15, "x"));
+    }
+
+    #[test]
+    fn expression_statements_with_effects_dont_warn() {
+        assert!(unused_result_warnings("fun f() {} f();").is_empty());
+        assert!(unused_result_warnings("var x; x = 1;").is_empty());
+        assert!(unused_result_warnings("class A {} var a = A(); a.b = 1; a.b;").is_empty());
+        assert!(unused_result_warnings("fun f() {} f() == 1;").is_empty());
+    }
+}
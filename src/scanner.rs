@@ -6,11 +6,16 @@ use crate::token::{Token, TokenType};
 use crate::utils::{is_alpha, is_alphanumeric, is_digit};
 
 pub struct Scanner {
-    source_code: String,
+    // Indexed by char, not by byte, so multi-byte characters take up one position like any other.
+    source_code: Vec<char>,
     tokens: Vec<Token>,
     current: usize,
     start: usize,
     line: i32,
+    // Index of the first char of the current line, so columns don't have to search back for it.
+    line_start: usize,
+    // Column of `start`, taken when the token begins since a string can span lines.
+    start_column: i32,
     keywords: HashMap<String, TokenType>,
 }
 
@@ -18,7 +23,9 @@ impl Scanner {
     pub fn new(source_code: &str) -> Self {
         let keywords = HashMap::from([
             ("and".to_string(), TokenType::And),
+            ("break".to_string(), TokenType::Break),
             ("class".to_string(), TokenType::Class),
+            ("continue".to_string(), TokenType::Continue),
             ("else".to_string(), TokenType::Else),
             ("false".to_string(), TokenType::False),
             ("for".to_string(), TokenType::For),
@@ -36,10 +43,12 @@ impl Scanner {
         ]);
         Scanner {
             tokens: Vec::<Token>::new(),
-            source_code: source_code.to_string(),
+            source_code: source_code.chars().collect(),
             current: 0,
             start: 0,
             line: 1,
+            line_start: 0,
+            start_column: 1,
             keywords,
         }
     }
@@ -49,21 +58,49 @@ impl Scanner {
     }
 
     fn advance(&mut self) -> char {
-        let c = self.source_code.chars().nth(self.current).unwrap();
+        let c = self.source_code[self.current];
         self.current += 1;
         c
     }
 
-    fn add_token(&mut self, token_type: TokenType, literal: Option<Object>) {
-        let mut lexeme = &self.source_code[self.start..self.current];
-        if token_type == TokenType::EOF {
-            lexeme = "";
+    fn text(&self, start: usize, end: usize) -> String {
+        self.source_code[start..end].iter().collect()
+    }
+
+    // `\n`, `\r\n` and a lone `\r` each end a line. In `\r\n` only the `\n` counts, so the pair
+    // isn't counted twice.
+    fn ends_line(&self, index: usize) -> bool {
+        match self.source_code[index] {
+            '\n' => true,
+            '\r' => self.source_code.get(index + 1) != Some(&'\n'),
+            _ => false,
         }
+    }
+
+    // Starts the next token at `current`. Columns are counted from 1 at the start of the line.
+    fn begin_token(&mut self) {
+        self.start = self.current;
+        self.start_column = (self.start - self.line_start + 1) as i32;
+    }
+
+    // Column of `current`. Errors found only once the source runs out are reported here, the
+    // token they're in can start lines earlier.
+    fn current_column(&self) -> i32 {
+        (self.current - self.line_start + 1) as i32
+    }
+
+    fn add_token(&mut self, token_type: TokenType, literal: Option<Object>) {
+        let lexeme = if token_type == TokenType::EOF {
+            String::new()
+        } else {
+            self.text(self.start, self.current)
+        };
         self.tokens.push(Token::new(
             token_type,
-            lexeme.to_string(),
+            lexeme,
             literal,
             self.line,
+            self.start_column,
         ));
     }
 
@@ -76,7 +113,7 @@ impl Scanner {
             return false;
         }
 
-        if self.source_code.chars().nth(self.current).unwrap() != expected {
+        if self.source_code[self.current] != expected {
             return false;
         }
 
@@ -88,40 +125,74 @@ impl Scanner {
         if self.is_at_end() {
             return '\0';
         }
-        return self.source_code.chars().nth(self.current).unwrap();
+        self.source_code[self.current]
     }
 
     fn peek_next(&mut self) -> char {
         if self.current + 1 >= self.source_code.len() {
             return '\0';
         }
-        self.source_code.chars().nth(self.current + 1).unwrap()
+        self.source_code[self.current + 1]
     }
 
-    fn new_line(&mut self) {
+    // `at` is the index of the char that ended the line.
+    fn new_line(&mut self, at: usize) {
         self.line += 1;
+        self.line_start = at + 1;
     }
 
     fn string(&mut self) -> Result<(), LoxResult> {
         while self.peek() != '"' && !self.is_at_end() {
-            if self.peek() == '\n' {
-                self.new_line();
+            // the escaped char is skipped along with the backslash, so `\"` doesn't end the string
+            if self.peek() == '\\' {
+                self.advance();
+                if self.is_at_end() {
+                    break;
+                }
+            }
+            if self.ends_line(self.current) {
+                self.new_line(self.current);
             }
             self.advance();
         }
 
         if self.is_at_end() {
-            return Err(LoxResult::scanner_error(self.line as usize, "Unterminated String."));
+            return Err(LoxResult::scanner_error(self.line as usize, self.current_column(), "Unterminated String."));
         }
 
         self.advance();
 
-        let literal = self.source_code[(self.start + 1)..(self.current - 1)].to_string();
+        let raw = self.text(self.start + 1, self.current - 1);
+        let literal = unescape(&raw)
+            .map_err(|message| LoxResult::scanner_error(self.line as usize, self.current_column(), &message))?;
         self.add_token(TokenType::StringLiteral, Some(Object::Str(literal)));
 
         Ok(())
     }
 
+    // Called after the opening `/*`. Comments nest, so every `/*` inside needs its own `*/`.
+    fn block_comment(&mut self) -> Result<(), LoxResult> {
+        let mut depth = 1;
+        while depth > 0 {
+            if self.is_at_end() {
+                return Err(LoxResult::scanner_error(
+                    self.line as usize,
+                    self.current_column(),
+                    "Unterminated block comment.",
+                ));
+            }
+            if self.ends_line(self.current) {
+                self.new_line(self.current);
+            }
+            match self.advance() {
+                '/' if self.expect('*') => depth += 1,
+                '*' if self.expect('/') => depth -= 1,
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
     fn number(&mut self) {
         while is_digit(self.peek()) {
             self.advance();
@@ -133,8 +204,8 @@ impl Scanner {
                 self.advance();
             }
         }
-        let literal: f64 = self.source_code[self.start..self.current]
-            .to_string()
+        let literal: f64 = self
+            .text(self.start, self.current)
             .parse()
             .unwrap();
         self.add_token(TokenType::NumberLiteral, Some(Object::Num(literal)));
@@ -145,10 +216,10 @@ impl Scanner {
             self.advance();
         }
 
-        let text = &self.source_code[self.start..self.current];
+        let text = self.text(self.start, self.current);
         let token_type = *self
             .keywords
-            .get(text)
+            .get(&text)
             .unwrap_or(&TokenType::Identifier);
 
         match token_type {
@@ -167,12 +238,25 @@ impl Scanner {
             ')' => self.add_token_single(TokenType::RightParen),
             '{' => self.add_token_single(TokenType::LeftBrace),
             '}' => self.add_token_single(TokenType::RightBrace),
+            '[' => self.add_token_single(TokenType::LeftBracket),
+            ']' => self.add_token_single(TokenType::RightBracket),
             ',' => self.add_token_single(TokenType::Comma),
             '.' => self.add_token_single(TokenType::Dot),
             '-' => self.add_token_single(TokenType::Minus),
             '+' => self.add_token_single(TokenType::Plus),
             ';' => self.add_token_single(TokenType::Semicolon),
-            '*' => self.add_token_single(TokenType::Star),
+            '*' => {
+                // `*/` can only close a block comment, outside of one it's a mistake. `*/*` is a
+                // multiplication followed by the start of a comment though.
+                if self.peek() == '/' && self.peek_next() != '*' {
+                    self.advance();
+                    return Err(LoxResult::scanner_error(self.line as usize, self.start_column, "Unexpected '*/'."));
+                }
+                self.add_token_single(TokenType::Star)
+            }
+            '%' => self.add_token_single(TokenType::Percent),
+            '?' => self.add_token_single(TokenType::Question),
+            ':' => self.add_token_single(TokenType::Colon),
             '!' => {
                 if self.expect('=') {
                     self.add_token_single(TokenType::BangEqual)
@@ -203,25 +287,22 @@ impl Scanner {
             }
             '/' => {
                 if self.expect('/') {
-                    while self.peek() != '\n' && !self.is_at_end() {
+                    while !self.is_at_end() && !self.ends_line(self.current) {
                         self.advance();
                     }
                 } else if self.expect('*') {
-                    while self.peek() != '*' && self.peek_next() != '/' {
-                        self.advance();
-                    }
-                    // skip the */
-                    self.advance();
-                    self.advance();
+                    self.block_comment()?;
                 } else {
                     self.add_token_single(TokenType::Slash)
                 }
             }
 
-            ' ' | '\t' | '\r' => {}
+            ' ' | '\t' => {}
 
-            '\n' => {
-                self.new_line();
+            '\n' | '\r' => {
+                if self.ends_line(self.current - 1) {
+                    self.new_line(self.current - 1);
+                }
             }
 
             '\"' => {
@@ -234,35 +315,137 @@ impl Scanner {
                 } else if is_alpha(c) {
                     self.identifier();
                 } else {
-                    return Err(LoxResult::scanner_error(self.line as usize, "Unexpected Character"));
+                    return Err(LoxResult::scanner_error(
+                        self.line as usize,
+                        self.start_column,
+                        &format!("Unexpected character '{}' (U+{:04X}).", c, c as u32),
+                    ));
                 }
             }
         }
         Ok(())
     }
 
-    pub fn tokenize(&mut self) -> Result<Vec<Token>, LoxResult> {
-        let mut had_error: Option<LoxResult> = None;
-
-        let emit_token = |token_type: TokenType| {
-            let lexeme = &self.source_code[self.start..self.current];
-        };
+    // Scanning carries on after an error, so one run finds every error in the source. They have
+    // all been reported already.
+    pub fn tokenize(&mut self) -> Result<Vec<Token>, Vec<LoxResult>> {
+        let mut errors = Vec::new();
 
         while !self.is_at_end() {
-            self.start = self.current;
+            self.begin_token();
             if let Err(e) = self.scan_token() {
-                e.report("");
-                had_error = Some(e);
+                errors.push(e);
             }
         }
+        self.begin_token();
         self.add_token_single(TokenType::EOF);
-        match had_error {
-            Some(e) => Err(e),
-            _ => Ok(self.tokens.clone()),
+        if errors.is_empty() {
+            Ok(self.tokens.clone())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+// Replaces the escape sequences in the text between a string's quotes: `\n`, `\t`, `\r`, `\"`,
+// `\\` and `\0`, and `\u{1F600}` for the code point with that hex value. Any other char after a
+// backslash is an error.
+fn unescape(raw: &str) -> Result<String, String> {
+    let mut literal = String::with_capacity(raw.len());
+    let mut chars = raw.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            literal.push(c);
+            continue;
+        }
+        let escaped = match chars.next() {
+            Some('n') => '\n',
+            Some('t') => '\t',
+            Some('r') => '\r',
+            Some('"') => '"',
+            Some('\\') => '\\',
+            Some('0') => '\0',
+            Some('u') => {
+                literal.push(unicode_escape(&mut chars)?);
+                continue;
+            }
+            Some(other) => return Err(format!("Unknown escape sequence '\\{other}'.")),
+            // `string` never ends the raw text with a lone backslash, it would escape the quote
+            None => return Err("Unterminated escape sequence.".to_string()),
+        };
+        literal.push(escaped);
+    }
+
+    Ok(literal)
+}
+
+// Called after `\\u`, reads the `{1F600}` part.
+fn unicode_escape(chars: &mut impl Iterator<Item = char>) -> Result<char, String> {
+    if chars.next() != Some('{') {
+        return Err("Expect '{' after '\\u'.".to_string());
+    }
+    let mut digits = String::new();
+    loop {
+        match chars.next() {
+            Some('}') => break,
+            Some(d) => digits.push(d),
+            None => return Err("Unterminated unicode escape.".to_string()),
+        }
+    }
+    if digits.is_empty() || digits.len() > 6 {
+        return Err(format!("Invalid unicode escape '\\u{{{digits}}}'."));
+    }
+    let code = u32::from_str_radix(&digits, 16)
+        .map_err(|_| format!("Invalid unicode escape '\\u{{{digits}}}'."))?;
+    char::from_u32(code)
+        .ok_or_else(|| format!("Unicode escape '\\u{{{digits}}}' is not a valid code point."))
+}
+
+// Scans lazily, one token per call to `next`, so a parser can pull tokens on demand instead of
+// holding all of them at once. An error takes the place of the text it couldn't scan and scanning
+// carries on after it. The last item is always the EOF token.
+pub struct Tokens {
+    scanner: Scanner,
+    done: bool,
+}
+
+impl IntoIterator for Scanner {
+    type Item = Result<Token, LoxResult>;
+    type IntoIter = Tokens;
+
+    fn into_iter(self) -> Tokens {
+        Tokens {
+            scanner: self,
+            done: false,
         }
     }
 }
 
+impl Iterator for Tokens {
+    type Item = Result<Token, LoxResult>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // whitespace and comments don't produce a token, so keep scanning until something does
+        while self.scanner.tokens.is_empty() {
+            if self.done {
+                return None;
+            }
+            if self.scanner.is_at_end() {
+                self.done = true;
+                self.scanner.begin_token();
+                self.scanner.add_token_single(TokenType::EOF);
+            } else {
+                self.scanner.begin_token();
+                if let Err(e) = self.scanner.scan_token() {
+                    return Some(Err(e));
+                }
+            }
+        }
+        Some(Ok(self.scanner.tokens.remove(0)))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -273,17 +456,18 @@ mod tests {
         let mut scanner = Scanner::new(&code);
 
         let expected: Vec<Token> = [
-            Token::new(TokenType::Var, "var".to_string(), None, 1),
-            Token::new(TokenType::Identifier, "x".to_string(), None, 1),
-            Token::new(TokenType::Equal, "=".to_string(), None, 1),
+            Token::new(TokenType::Var, "var".to_string(), None, 1, 1),
+            Token::new(TokenType::Identifier, "x".to_string(), None, 1, 5),
+            Token::new(TokenType::Equal, "=".to_string(), None, 1, 7),
             Token::new(
                 TokenType::NumberLiteral,
                 "10".to_string(),
-                Some(Object::Num(10 as f64)),
+                Some(Object::Num(10_f64)),
                 1,
+                9,
             ),
-            Token::new(TokenType::Semicolon, ";".to_string(), None, 1),
-            Token::new(TokenType::EOF, "".to_string(), None, 2),
+            Token::new(TokenType::Semicolon, ";".to_string(), None, 1, 11),
+            Token::new(TokenType::EOF, "".to_string(), None, 2, 1),
         ]
         .into_iter()
         .collect();
@@ -301,17 +485,18 @@ mod tests {
         let mut scanner = Scanner::new(&code);
 
         let expected: Vec<Token> = [
-            Token::new(TokenType::Var, "var".to_string(), None, 1),
-            Token::new(TokenType::Identifier, "x".to_string(), None, 1),
-            Token::new(TokenType::Equal, "=".to_string(), None, 1),
+            Token::new(TokenType::Var, "var".to_string(), None, 1, 1),
+            Token::new(TokenType::Identifier, "x".to_string(), None, 1, 5),
+            Token::new(TokenType::Equal, "=".to_string(), None, 1, 7),
             Token::new(
                 TokenType::StringLiteral,
                 "\"hallo\"".to_string(),
                 Some(Object::Str("hallo".to_string())),
                 1,
+                9,
             ),
-            Token::new(TokenType::Semicolon, ";".to_string(), None, 1),
-            Token::new(TokenType::EOF, "".to_string(), None, 2),
+            Token::new(TokenType::Semicolon, ";".to_string(), None, 1, 16),
+            Token::new(TokenType::EOF, "".to_string(), None, 2, 1),
         ]
         .into_iter()
         .collect();
@@ -328,17 +513,18 @@ mod tests {
         let mut scanner = Scanner::new(&code);
 
         let expected: Vec<Token> = [
-            Token::new(TokenType::Var, "var".to_string(), None, 1),
-            Token::new(TokenType::Identifier, "x".to_string(), None, 1),
-            Token::new(TokenType::Equal, "=".to_string(), None, 1),
+            Token::new(TokenType::Var, "var".to_string(), None, 1, 1),
+            Token::new(TokenType::Identifier, "x".to_string(), None, 1, 5),
+            Token::new(TokenType::Equal, "=".to_string(), None, 1, 7),
             Token::new(
                 TokenType::True,
                 "true".to_string(),
                 Some(Object::Bool(true)),
                 1,
+                9,
             ),
-            Token::new(TokenType::Semicolon, ";".to_string(), None, 1),
-            Token::new(TokenType::EOF, "".to_string(), None, 2),
+            Token::new(TokenType::Semicolon, ";".to_string(), None, 1, 13),
+            Token::new(TokenType::EOF, "".to_string(), None, 2, 1),
         ]
         .into_iter()
         .collect();
@@ -349,4 +535,181 @@ mod tests {
             .zip(&expected)
             .for_each(|(a, b)| assert_eq!(a, b));
     }
+
+    // The line, column and message of each error scanning `code` runs into.
+    fn scan_errors(code: &str) -> Vec<(usize, i32, String)> {
+        match Scanner::new(code).tokenize() {
+            Err(errors) => errors
+                .into_iter()
+                .map(|e| match e {
+                    LoxResult::Error { line, column, message } => (line, column, message),
+                    other => panic!("expected a scanner error, got {:?}", other),
+                })
+                .collect(),
+            Ok(_) => panic!("expected scanner errors"),
+        }
+    }
+
+    #[test]
+    fn unexpected_character_is_named() {
+        assert_eq!(
+            scan_errors("var x = 1 # 2;"),
+            [(1, 11, "Unexpected character '#' (U+0023).".to_string())]
+        );
+    }
+
+    #[test]
+    fn all_errors_are_collected() {
+        assert_eq!(
+            scan_errors("var x = 1 # 2;\nprint x;\nprint ^;"),
+            [
+                (1, 11, "Unexpected character '#' (U+0023).".to_string()),
+                (3, 7, "Unexpected character '^' (U+005E).".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn lazy_tokens_match_tokenize() {
+        let code = "// a comment\nfun add(a, b) {\n  return a + b;\n}\nprint add(1, 2.5) >= 3 and \"s\" != nil;\n";
+
+        let eager = Scanner::new(code).tokenize().unwrap();
+        let lazy = Scanner::new(code)
+            .into_iter()
+            .collect::<Result<Vec<Token>, LoxResult>>()
+            .unwrap();
+
+        assert_eq!(eager, lazy);
+        assert_eq!(lazy.last().unwrap().token_type, TokenType::EOF);
+    }
+
+    #[test]
+    fn lazy_tokens_continue_after_error() {
+        let tokens = Scanner::new("1 # 2").into_iter().collect::<Vec<_>>();
+
+        assert_eq!(tokens.len(), 4);
+        assert!(tokens[1].is_err());
+        assert_eq!(tokens[2].as_ref().unwrap().lexeme, "2");
+    }
+
+    fn string_literal(code: &str) -> Object {
+        let tokens = Scanner::new(code).tokenize().unwrap();
+        tokens[0].literal.clone().unwrap()
+    }
+
+    #[test]
+    fn unicode_escape() {
+        assert_eq!(string_literal(r#""\u{41}BC""#), Object::Str("ABC".to_string()));
+        assert_eq!(string_literal(r#""hi \u{1F600}""#), Object::Str("hi \u{1F600}".to_string()));
+    }
+
+    #[test]
+    fn malformed_unicode_escapes() {
+        for code in [r#""\u{110000}""#, r#""\u{}""#, r#""\u{zz}""#, r#""\u41""#, r#""\u{41""#] {
+            let errors = scan_errors(code);
+            assert_eq!(errors.len(), 1, "expected one error for {code}");
+            assert_eq!(errors[0].0, 1);
+        }
+    }
+
+    #[test]
+    fn escape_sequences() {
+        assert_eq!(string_literal(r#""a\tb""#), Object::Str("a\tb".to_string()));
+        assert_eq!(string_literal(r#""say \"hi\"""#), Object::Str("say \"hi\"".to_string()));
+        assert_eq!(string_literal(r#""a\nb\r\0""#), Object::Str("a\nb\r\0".to_string()));
+        assert_eq!(string_literal(r#""C:\\dir\\""#), Object::Str("C:\\dir\\".to_string()));
+    }
+
+    #[test]
+    fn unknown_escape_is_an_error() {
+        assert_eq!(scan_errors(r#""a\qb""#), [(1, 7, "Unknown escape sequence '\\q'.".to_string())]);
+        // an escaped quote doesn't close the string
+        assert_eq!(scan_errors(r#""abc\""#), [(1, 7, "Unterminated String.".to_string())]);
+    }
+
+    #[test]
+    fn nested_block_comment() {
+        let code = "var a = 1; /* outer\n /* inner\n */ still * / outer\n */ var b = 2;";
+        let tokens = Scanner::new(code).tokenize().unwrap();
+
+        let lexemes = tokens.iter().map(|t| t.lexeme.as_str()).collect::<Vec<_>>();
+        assert_eq!(lexemes, ["var", "a", "=", "1", ";", "var", "b", "=", "2", ";", ""]);
+        // the newlines inside the comment are counted
+        assert_eq!(tokens[5].line, 4);
+    }
+
+    #[test]
+    fn unterminated_block_comment() {
+        assert_eq!(
+            scan_errors("print 1;\n/* /* */\n"),
+            [(3, 1, "Unterminated block comment.".to_string())]
+        );
+    }
+
+    #[test]
+    fn stray_block_comment_close() {
+        assert_eq!(
+            scan_errors("/* a */ print 1;\nprint 2; */\n"),
+            [(2, 10, "Unexpected '*/'.".to_string())]
+        );
+        assert!(Scanner::new("print a*/* c */b;").tokenize().is_ok());
+    }
+
+    #[test]
+    fn non_ascii_string_and_identifier() {
+        let tokens = Scanner::new("var café = \"crème brûlée\";").tokenize().unwrap();
+
+        assert_eq!(tokens[1].token_type, TokenType::Identifier);
+        assert_eq!(tokens[1].lexeme, "café");
+        assert_eq!(tokens[3].lexeme, "\"crème brûlée\"");
+        assert_eq!(tokens[3].literal, Some(Object::Str("crème brûlée".to_string())));
+        assert_eq!(tokens[4].token_type, TokenType::Semicolon);
+    }
+
+    #[test]
+    fn non_ascii_unexpected_character() {
+        assert_eq!(
+            scan_errors("1 € 2"),
+            [(1, 3, "Unexpected character '€' (U+20AC).".to_string())]
+        );
+    }
+
+    #[test]
+    fn tokens_know_their_column() {
+        let tokens = Scanner::new("print 1;\n  a = \"x\ny\" + b;").tokenize().unwrap();
+
+        // `1` is the second token on the first line
+        assert_eq!((tokens[1].lexeme.as_str(), tokens[1].line, tokens[1].column), ("1", 1, 7));
+        assert_eq!((tokens[4].lexeme.as_str(), tokens[4].column), ("=", 5));
+        // a string spanning lines starts where its opening quote is
+        assert_eq!(tokens[5].column, 7);
+        assert_eq!((tokens[7].lexeme.as_str(), tokens[7].line, tokens[7].column), ("b", 3, 6));
+    }
+
+    #[test]
+    fn crlf_and_lone_cr_count_lines_like_lf() {
+        let positions = |code: &str| {
+            Scanner::new(code)
+                .tokenize()
+                .unwrap()
+                .iter()
+                .map(|token| (token.line, token.column))
+                .collect::<Vec<_>>()
+        };
+        let lf = positions("var a = 1;\n// comment\n/* block\n*/ print \"x\ny\";\n  a;");
+
+        assert_eq!(positions("var a = 1;\r\n// comment\r\n/* block\r\n*/ print \"x\r\ny\";\r\n  a;"), lf);
+        assert_eq!(positions("var a = 1;\r// comment\r/* block\r*/ print \"x\ry\";\r  a;"), lf);
+        assert_eq!(lf.last(), Some(&(6, 5)));
+    }
+
+    #[test]
+    fn columns_on_a_long_line() {
+        let code = format!("{}\r\n  y;", "x = 1; ".repeat(1000));
+        let tokens = Scanner::new(&code).tokenize().unwrap();
+
+        // the last `x` comes after 999 statements of 7 chars each
+        assert_eq!((tokens[3996].lexeme.as_str(), tokens[3996].column), ("x", 6994));
+        assert_eq!((tokens[4000].lexeme.as_str(), tokens[4000].line, tokens[4000].column), ("y", 2, 3));
+    }
 }
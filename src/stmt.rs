@@ -6,9 +6,12 @@ use std::rc::Rc;
 use std::hash::{Hash, Hasher};
 use crate::expr::*;
 
+#[derive(Clone)]
 pub enum Stmt {
     Block(Rc<BlockStmt>),
+    Break(Rc<BreakStmt>),
     Class(Rc<ClassStmt>),
+    Continue(Rc<ContinueStmt>),
     Expression(Rc<ExpressionStmt>),
     Function(Rc<FunctionStmt>),
     If(Rc<IfStmt>),
@@ -22,7 +25,9 @@ impl PartialEq for Stmt {
     fn eq(&self, other: &Self) -> bool {
         match (self, other) {
                   (Stmt::Block(a), Stmt::Block(b)) => Rc::ptr_eq(a, b),
+                  (Stmt::Break(a), Stmt::Break(b)) => Rc::ptr_eq(a, b),
                   (Stmt::Class(a), Stmt::Class(b)) => Rc::ptr_eq(a, b),
+                  (Stmt::Continue(a), Stmt::Continue(b)) => Rc::ptr_eq(a, b),
                   (Stmt::Expression(a), Stmt::Expression(b)) => Rc::ptr_eq(a, b),
                   (Stmt::Function(a), Stmt::Function(b)) => Rc::ptr_eq(a, b),
                   (Stmt::If(a), Stmt::If(b)) => Rc::ptr_eq(a, b),
@@ -46,9 +51,15 @@ impl Hash for Stmt {
         Stmt::Block(a) => {
             hasher.write_usize(Rc::as_ptr(a) as usize);
         },
+        Stmt::Break(a) => {
+            hasher.write_usize(Rc::as_ptr(a) as usize);
+        },
         Stmt::Class(a) => {
             hasher.write_usize(Rc::as_ptr(a) as usize);
         },
+        Stmt::Continue(a) => {
+            hasher.write_usize(Rc::as_ptr(a) as usize);
+        },
         Stmt::Expression(a) => {
             hasher.write_usize(Rc::as_ptr(a) as usize);
         },
@@ -77,7 +88,9 @@ impl Stmt {
     pub fn accept<T>(&self, wrapper: Rc<Stmt>, visitor: &dyn StmtVisitor<T>) -> Result<T, LoxResult> {
         match self {
             Stmt::Block(x) => visitor.visit_block_stmt(wrapper, x),
+            Stmt::Break(x) => visitor.visit_break_stmt(wrapper, x),
             Stmt::Class(x) => visitor.visit_class_stmt(wrapper, x),
+            Stmt::Continue(x) => visitor.visit_continue_stmt(wrapper, x),
             Stmt::Expression(x) => visitor.visit_expression_stmt(wrapper, x),
             Stmt::Function(x) => visitor.visit_function_stmt(wrapper, x),
             Stmt::If(x) => visitor.visit_if_stmt(wrapper, x),
@@ -90,32 +103,47 @@ impl Stmt {
 }
 pub struct BlockStmt {
     pub statements: Rc<Vec<Rc<Stmt>>>,
+    pub line: i32,
+}
+
+pub struct BreakStmt {
+    pub keyword: Token,
 }
 
 pub struct ClassStmt {
     pub name: Token,
     pub superclass: Option<Rc<Expr>>,
+    pub fields: Rc<Vec<Rc<Stmt>>>,
     pub methods: Rc<Vec<Rc<Stmt>>>,
+    pub static_methods: Rc<Vec<Rc<Stmt>>>,
+}
+
+pub struct ContinueStmt {
+    pub keyword: Token,
 }
 
 pub struct ExpressionStmt {
     pub expression: Rc<Expr>,
+    pub line: i32,
 }
 
 pub struct FunctionStmt {
     pub name: Token,
     pub params: Rc<Vec<Token>>,
     pub body: Rc<Vec<Rc<Stmt>>>,
+    pub is_getter: bool,
 }
 
 pub struct IfStmt {
     pub condition: Rc<Expr>,
     pub then_branch: Rc<Stmt>,
     pub else_branch: Option<Rc<Stmt>>,
+    pub line: i32,
 }
 
 pub struct PrintStmt {
     pub expression: Rc<Expr>,
+    pub line: i32,
 }
 
 pub struct ReturnStmt {
@@ -131,11 +159,16 @@ pub struct VarStmt {
 pub struct WhileStmt {
     pub condition: Rc<Expr>,
     pub body: Rc<Stmt>,
+    pub increment: Option<Rc<Expr>>,
+    pub loop_variables: Vec<Token>,
+    pub line: i32,
 }
 
 pub trait StmtVisitor<T> {
     fn visit_block_stmt(&self, wrapper: Rc<Stmt>, stmt: &BlockStmt) -> Result<T, LoxResult>;
+    fn visit_break_stmt(&self, wrapper: Rc<Stmt>, stmt: &BreakStmt) -> Result<T, LoxResult>;
     fn visit_class_stmt(&self, wrapper: Rc<Stmt>, stmt: &ClassStmt) -> Result<T, LoxResult>;
+    fn visit_continue_stmt(&self, wrapper: Rc<Stmt>, stmt: &ContinueStmt) -> Result<T, LoxResult>;
     fn visit_expression_stmt(&self, wrapper: Rc<Stmt>, stmt: &ExpressionStmt) -> Result<T, LoxResult>;
     fn visit_function_stmt(&self, wrapper: Rc<Stmt>, stmt: &FunctionStmt) -> Result<T, LoxResult>;
     fn visit_if_stmt(&self, wrapper: Rc<Stmt>, stmt: &IfStmt) -> Result<T, LoxResult>;
@@ -9,6 +9,8 @@ pub enum TokenType {
     RightParen,
     LeftBrace,
     RightBrace,
+    LeftBracket,
+    RightBracket,
     Comma,
     Dot,
     Minus,
@@ -16,6 +18,9 @@ pub enum TokenType {
     Semicolon,
     Slash,
     Star,
+    Percent,
+    Question,
+    Colon,
 
     // One or two character tokens.
     Bang,
@@ -34,7 +39,9 @@ pub enum TokenType {
 
     // Keywords.
     And,
+    Break,
     Class,
+    Continue,
     Else,
     False,
     Fun,
@@ -59,15 +66,18 @@ pub struct Token {
     pub lexeme: String,
     pub literal: Option<Object>,
     pub line: i32,
+    // Counted in chars from 1, 0 for tokens that aren't from the source.
+    pub column: i32,
 }
 
 impl Token {
-    pub fn new(token_type: TokenType, lexeme: String, literal: Option<Object>, line: i32) -> Self {
+    pub fn new(token_type: TokenType, lexeme: String, literal: Option<Object>, line: i32, column: i32) -> Self {
         Token {
             token_type,
             lexeme,
             literal,
             line,
+            column,
         }
     }
 }
@@ -1,9 +1,9 @@
 pub fn is_digit(c: char) -> bool {
-    ('0'..='9').contains(&c)
+    c.is_ascii_digit()
 }
 
 pub fn is_alpha(c: char) -> bool {
-    ('a'..='z').contains(&c) || ('A'..='Z').contains(&c) || c == '_'
+    c.is_alphabetic() || c == '_'
 }
 
 pub fn is_alphanumeric(c: char) -> bool {
@@ -6,7 +6,14 @@ for file in ./tests/*; do
     fi
     cargo run $file
 
-    if [[ $? -ne 0 ]]; then
+    status=$?
+    # files ending in _fail are expected to be rejected
+    if [[ "$file" == *_fail ]]; then
+        if [[ $status -eq 0 ]]; then
+            echo "$file DID NOT FAIL"
+            exit 1
+        fi
+    elif [[ $status -ne 0 ]]; then
         echo "$file FAILED"
         exit 1
     fi